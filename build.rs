@@ -0,0 +1,79 @@
+//! Turns `src/cpu/instructions.in` — one line per opcode of
+//! `opcode|cb|mnemonic|length|cycles_taken|cycles_untaken` — into the
+//! generated lookup tables `src/cpu/instructions.rs` includes for
+//! disassembly and cycle-cost auditing. The decode tables that build
+//! `Instruction` values stay hand-written (they're already a compact,
+//! bitfield-driven match rather than a flat 256-entry table), but the
+//! per-opcode metadata that `instructions.in` captures had no single
+//! source of truth before this, so it's generated from the table instead.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let table_path = Path::new(&manifest_dir).join("src/cpu/instructions.in");
+    println!("cargo:rerun-if-changed={}", table_path.display());
+
+    let table = fs::read_to_string(&table_path).expect("failed to read src/cpu/instructions.in");
+
+    let mut not_prefixed = vec![None; 256];
+    let mut prefixed = vec![None; 256];
+
+    for line in table.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.splitn(6, '|').collect();
+        let [opcode, cb, mnemonic, length, taken, untaken] = fields[..] else {
+            panic!("malformed instructions.in row: {}", line);
+        };
+
+        let opcode = u8::from_str_radix(opcode.trim_start_matches("0x"), 16)
+            .unwrap_or_else(|_| panic!("bad opcode in row: {}", line));
+        let entry = (
+            mnemonic.to_string(),
+            length.parse::<u8>().unwrap(),
+            taken.parse::<u8>().unwrap(),
+            untaken.parse::<u8>().unwrap(),
+        );
+
+        if cb == "1" {
+            prefixed[opcode as usize] = Some(entry);
+        } else {
+            not_prefixed[opcode as usize] = Some(entry);
+        }
+    }
+
+    let mut generated = String::new();
+    generated.push_str("/// Per-opcode `(mnemonic, length, cycles_taken, cycles_untaken)`, generated\n");
+    generated.push_str("/// from `instructions.in` by `build.rs`. `cycles_taken` and\n");
+    generated.push_str("/// `cycles_untaken` only differ for conditional `JP`/`JR`/`CALL`/`RET`.\n");
+    generated.push_str("pub type OpcodeMeta = (&'static str, u8, u8, u8);\n\n");
+
+    emit_table(&mut generated, "OPCODE_META", &not_prefixed);
+    emit_table(&mut generated, "OPCODE_META_PREFIXED", &prefixed);
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("opcode_metadata.rs"), generated)
+        .expect("failed to write generated opcode metadata");
+}
+
+fn emit_table(out: &mut String, name: &str, entries: &[Option<(String, u8, u8, u8)>]) {
+    out.push_str(&format!("pub static {}: [OpcodeMeta; 256] = [\n", name));
+
+    for entry in entries {
+        let (mnemonic, length, taken, untaken) = entry
+            .as_ref()
+            .expect("instructions.in is missing a row for an opcode");
+        out.push_str(&format!(
+            "    ({:?}, {}, {}, {}),\n",
+            mnemonic, length, taken, untaken
+        ));
+    }
+
+    out.push_str("];\n\n");
+}
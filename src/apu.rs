@@ -0,0 +1,575 @@
+pub const APU_REGISTERS_BEGIN: u16 = 0xFF10;
+pub const APU_REGISTERS_END: u16 = 0xFF3F;
+const REGISTER_COUNT: usize = (APU_REGISTERS_END - APU_REGISTERS_BEGIN + 1) as usize;
+const WAVE_RAM_BEGIN: u16 = 0xFF30;
+
+const CPU_FREQUENCY: u32 = 4_194_304;
+const SAMPLE_RATE: u32 = 44_100;
+const FRAME_SEQUENCER_PERIOD: u32 = CPU_FREQUENCY / 512;
+/// Samples are buffered and not handed to the host until this many have
+/// accumulated, so the first frames don't starve the audio device.
+const MIN_BUFFERED_SAMPLES: usize = 512;
+
+const SQUARE_DUTY_TABLE: [[u8; 8]; 4] = [
+    [0, 0, 0, 0, 0, 0, 0, 1],
+    [1, 0, 0, 0, 0, 0, 0, 1],
+    [1, 0, 0, 0, 0, 1, 1, 1],
+    [0, 1, 1, 1, 1, 1, 1, 0],
+];
+
+#[derive(Default)]
+struct SquareChannel {
+    enabled: bool,
+    with_sweep: bool,
+    duty: u8,
+    duty_step: u8,
+    frequency_timer: i32,
+    length_timer: u8,
+    volume: u8,
+    envelope_timer: u8,
+    sweep_timer: u8,
+    shadow_frequency: u16,
+    sweep_enabled: bool,
+}
+
+impl SquareChannel {
+    fn frequency(&self, registers: &[u8], base: u16) -> u16 {
+        let lo = registers[(base - APU_REGISTERS_BEGIN) as usize] as u16;
+        let hi = registers[(base + 1 - APU_REGISTERS_BEGIN) as usize] as u16;
+
+        lo | ((hi & 0x07) << 8)
+    }
+
+    fn trigger(&mut self, registers: &[u8], base: u16) {
+        self.enabled = true;
+        self.frequency_timer = (2048 - self.frequency(registers, base) as i32) * 4;
+        self.duty_step = 0;
+
+        let envelope = registers[(base - 1 - APU_REGISTERS_BEGIN) as usize];
+        self.volume = envelope >> 4;
+        self.envelope_timer = envelope & 0x07;
+
+        let duty = registers[(base - 2 - APU_REGISTERS_BEGIN) as usize];
+        self.duty = (duty >> 6) & 0x03;
+
+        if self.with_sweep {
+            self.shadow_frequency = self.frequency(registers, base);
+            let sweep = registers[(base - 3 - APU_REGISTERS_BEGIN) as usize];
+            self.sweep_timer = (sweep >> 4) & 0x07;
+            self.sweep_enabled = self.sweep_timer != 0 || (sweep & 0x07) != 0;
+        }
+    }
+
+    fn step(&mut self, registers: &[u8], base: u16, t_cycles: i32) {
+        if !self.enabled {
+            return;
+        }
+
+        self.frequency_timer -= t_cycles;
+
+        while self.frequency_timer <= 0 {
+            self.frequency_timer += (2048 - self.frequency(registers, base) as i32) * 4;
+            self.duty_step = (self.duty_step + 1) % 8;
+        }
+    }
+
+    fn clock_length(&mut self, length_enabled: bool) {
+        if length_enabled && self.length_timer > 0 {
+            self.length_timer -= 1;
+            if self.length_timer == 0 {
+                self.enabled = false;
+            }
+        }
+    }
+
+    fn clock_envelope(&mut self, registers: &[u8], base: u16) {
+        let envelope = registers[(base - 1 - APU_REGISTERS_BEGIN) as usize];
+        let period = envelope & 0x07;
+
+        if period == 0 {
+            return;
+        }
+
+        if self.envelope_timer > 0 {
+            self.envelope_timer -= 1;
+
+            if self.envelope_timer == 0 {
+                self.envelope_timer = period;
+                let increase = envelope & 0x08 != 0;
+
+                if increase && self.volume < 15 {
+                    self.volume += 1;
+                } else if !increase && self.volume > 0 {
+                    self.volume -= 1;
+                }
+            }
+        }
+    }
+
+    fn amplitude(&self) -> f32 {
+        if !self.enabled {
+            return 0.0;
+        }
+
+        let active = SQUARE_DUTY_TABLE[self.duty as usize][self.duty_step as usize];
+
+        if active == 1 {
+            self.volume as f32 / 15.0
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Default)]
+struct WaveChannel {
+    enabled: bool,
+    position: usize,
+    frequency_timer: i32,
+    length_timer: u16,
+}
+
+impl WaveChannel {
+    fn frequency(registers: &[u8]) -> u16 {
+        let lo = registers[(0xFF1D - APU_REGISTERS_BEGIN) as usize] as u16;
+        let hi = registers[(0xFF1E - APU_REGISTERS_BEGIN) as usize] as u16;
+
+        lo | ((hi & 0x07) << 8)
+    }
+
+    fn trigger(&mut self, registers: &[u8]) {
+        self.enabled = registers[(0xFF1A - APU_REGISTERS_BEGIN) as usize] & 0x80 != 0;
+        self.frequency_timer = (2048 - Self::frequency(registers) as i32) * 2;
+        self.position = 0;
+    }
+
+    fn step(&mut self, registers: &[u8], t_cycles: i32) {
+        if !self.enabled {
+            return;
+        }
+
+        self.frequency_timer -= t_cycles;
+
+        while self.frequency_timer <= 0 {
+            self.frequency_timer += (2048 - Self::frequency(registers) as i32) * 2;
+            self.position = (self.position + 1) % 32;
+        }
+    }
+
+    fn clock_length(&mut self, length_enabled: bool) {
+        if length_enabled && self.length_timer > 0 {
+            self.length_timer -= 1;
+            if self.length_timer == 0 {
+                self.enabled = false;
+            }
+        }
+    }
+
+    fn amplitude(&self, registers: &[u8]) -> f32 {
+        if !self.enabled {
+            return 0.0;
+        }
+
+        let wave_ram = &registers[(WAVE_RAM_BEGIN - APU_REGISTERS_BEGIN) as usize..];
+        let byte = wave_ram[self.position / 2];
+        let nibble = if self.position.is_multiple_of(2) {
+            byte >> 4
+        } else {
+            byte & 0x0F
+        };
+
+        let shift = match (registers[(0xFF1C - APU_REGISTERS_BEGIN) as usize] >> 5) & 0x03 {
+            0 => 4,
+            1 => 0,
+            2 => 1,
+            _ => 2,
+        };
+
+        (nibble >> shift) as f32 / 15.0
+    }
+}
+
+#[derive(Default)]
+struct NoiseChannel {
+    enabled: bool,
+    lfsr: u16,
+    frequency_timer: i32,
+    length_timer: u8,
+    volume: u8,
+    envelope_timer: u8,
+}
+
+impl NoiseChannel {
+    fn trigger(&mut self, registers: &[u8]) {
+        self.enabled = true;
+        self.lfsr = 0x7FFF;
+        self.frequency_timer = Self::divisor(registers);
+
+        let envelope = registers[(0xFF21 - APU_REGISTERS_BEGIN) as usize];
+        self.volume = envelope >> 4;
+        self.envelope_timer = envelope & 0x07;
+    }
+
+    fn divisor(registers: &[u8]) -> i32 {
+        let poly = registers[(0xFF22 - APU_REGISTERS_BEGIN) as usize];
+        let divisor_code = (poly & 0x07) as i32;
+        let shift = (poly >> 4) as i32;
+        let base_divisor = if divisor_code == 0 { 8 } else { divisor_code * 16 };
+
+        base_divisor << shift
+    }
+
+    fn step(&mut self, registers: &[u8], t_cycles: i32) {
+        if !self.enabled {
+            return;
+        }
+
+        self.frequency_timer -= t_cycles;
+
+        while self.frequency_timer <= 0 {
+            self.frequency_timer += Self::divisor(registers).max(1);
+
+            let narrow = registers[(0xFF22 - APU_REGISTERS_BEGIN) as usize] & 0x08 != 0;
+            let xor_bit = (self.lfsr & 0x01) ^ ((self.lfsr >> 1) & 0x01);
+
+            self.lfsr = (self.lfsr >> 1) | (xor_bit << 14);
+
+            if narrow {
+                self.lfsr &= !(1 << 6);
+                self.lfsr |= xor_bit << 6;
+            }
+        }
+    }
+
+    fn clock_length(&mut self, length_enabled: bool) {
+        if length_enabled && self.length_timer > 0 {
+            self.length_timer -= 1;
+            if self.length_timer == 0 {
+                self.enabled = false;
+            }
+        }
+    }
+
+    fn clock_envelope(&mut self, registers: &[u8]) {
+        let envelope = registers[(0xFF21 - APU_REGISTERS_BEGIN) as usize];
+        let period = envelope & 0x07;
+
+        if period == 0 {
+            return;
+        }
+
+        if self.envelope_timer > 0 {
+            self.envelope_timer -= 1;
+
+            if self.envelope_timer == 0 {
+                self.envelope_timer = period;
+                let increase = envelope & 0x08 != 0;
+
+                if increase && self.volume < 15 {
+                    self.volume += 1;
+                } else if !increase && self.volume > 0 {
+                    self.volume -= 1;
+                }
+            }
+        }
+    }
+
+    fn amplitude(&self) -> f32 {
+        if !self.enabled || self.lfsr & 0x01 != 0 {
+            0.0
+        } else {
+            self.volume as f32 / 15.0
+        }
+    }
+}
+
+/// A one-pole high-pass/low-pass filter pair so DC offset and the startup
+/// transient don't produce a high-pitched ring in the mixed output.
+#[derive(Default)]
+struct OutputFilter {
+    high_pass_capacitor: f32,
+    low_pass_previous: f32,
+}
+
+impl OutputFilter {
+    const HIGH_PASS_CHARGE_FACTOR: f32 = 0.996;
+    const LOW_PASS_CUTOFF: f32 = 0.15;
+
+    fn process(&mut self, input: f32) -> f32 {
+        let high_passed = input - self.high_pass_capacitor;
+        self.high_pass_capacitor = input - high_passed * Self::HIGH_PASS_CHARGE_FACTOR;
+
+        self.low_pass_previous +=
+            Self::LOW_PASS_CUTOFF * (high_passed - self.low_pass_previous);
+
+        self.low_pass_previous
+    }
+}
+
+/// The DMG's four-channel sound generator: two square channels (one with a
+/// frequency sweep), a wave channel backed by the 0xFF30-0xFF3F wave RAM,
+/// and a noise channel driven by an LFSR. Clocked by the same cycle counter
+/// the CPU accumulates and stepped internally by a 512 Hz frame sequencer.
+pub struct APU {
+    registers: [u8; REGISTER_COUNT],
+    square1: SquareChannel,
+    square2: SquareChannel,
+    wave: WaveChannel,
+    noise: NoiseChannel,
+    frame_sequencer_timer: u32,
+    frame_sequencer_step: u8,
+    sample_timer: u32,
+    filter: OutputFilter,
+    sample_buffer: Vec<f32>,
+}
+
+impl Default for APU {
+    fn default() -> Self {
+        APU {
+            registers: [0; REGISTER_COUNT],
+            square1: SquareChannel {
+                with_sweep: true,
+                ..SquareChannel::default()
+            },
+            square2: SquareChannel::default(),
+            wave: WaveChannel::default(),
+            noise: NoiseChannel::default(),
+            frame_sequencer_timer: FRAME_SEQUENCER_PERIOD,
+            frame_sequencer_step: 0,
+            sample_timer: 0,
+            filter: OutputFilter::default(),
+            sample_buffer: Vec::new(),
+        }
+    }
+}
+
+impl APU {
+    pub fn read_byte(&self, address: u16) -> u8 {
+        self.registers[(address - APU_REGISTERS_BEGIN) as usize]
+    }
+
+    pub fn write_byte(&mut self, address: u16, value: u8) {
+        self.registers[(address - APU_REGISTERS_BEGIN) as usize] = value;
+
+        match address {
+            0xFF14 if value & 0x80 != 0 => self.square1.trigger(&self.registers, 0xFF13),
+            0xFF19 if value & 0x80 != 0 => self.square2.trigger(&self.registers, 0xFF18),
+            0xFF1E if value & 0x80 != 0 => self.wave.trigger(&self.registers),
+            0xFF23 if value & 0x80 != 0 => self.noise.trigger(&self.registers),
+            _ => {}
+        }
+    }
+
+    /// Advances every channel and the frame sequencer by the M-cycles the
+    /// CPU just spent, mixing new samples into the output buffer as the
+    /// host sample clock comes due.
+    pub fn step(&mut self, m_cycles: u8) {
+        let t_cycles = m_cycles as i32 * 4;
+
+        self.square1.step(&self.registers, 0xFF13, t_cycles);
+        self.square2.step(&self.registers, 0xFF18, t_cycles);
+        self.wave.step(&self.registers, t_cycles);
+        self.noise.step(&self.registers, t_cycles);
+
+        self.frame_sequencer_timer = self.frame_sequencer_timer.saturating_sub(t_cycles as u32);
+        if self.frame_sequencer_timer == 0 {
+            self.frame_sequencer_timer = FRAME_SEQUENCER_PERIOD;
+            self.clock_frame_sequencer();
+        }
+
+        self.sample_timer += t_cycles as u32;
+        let cycles_per_sample = CPU_FREQUENCY / SAMPLE_RATE;
+
+        while self.sample_timer >= cycles_per_sample {
+            self.sample_timer -= cycles_per_sample;
+            self.mix_sample();
+        }
+    }
+
+    fn clock_frame_sequencer(&mut self) {
+        let length_enabled_1 = self.registers[(0xFF14 - APU_REGISTERS_BEGIN) as usize] & 0x40 != 0;
+        let length_enabled_2 = self.registers[(0xFF19 - APU_REGISTERS_BEGIN) as usize] & 0x40 != 0;
+        let length_enabled_3 = self.registers[(0xFF1E - APU_REGISTERS_BEGIN) as usize] & 0x40 != 0;
+        let length_enabled_4 = self.registers[(0xFF23 - APU_REGISTERS_BEGIN) as usize] & 0x40 != 0;
+
+        if self.frame_sequencer_step.is_multiple_of(2) {
+            self.square1.clock_length(length_enabled_1);
+            self.square2.clock_length(length_enabled_2);
+            self.wave.clock_length(length_enabled_3);
+            self.noise.clock_length(length_enabled_4);
+        }
+
+        if self.frame_sequencer_step % 4 == 2 {
+            self.clock_sweep();
+        }
+
+        if self.frame_sequencer_step == 7 {
+            self.square1.clock_envelope(&self.registers, 0xFF13);
+            self.square2.clock_envelope(&self.registers, 0xFF18);
+            self.noise.clock_envelope(&self.registers);
+        }
+
+        self.frame_sequencer_step = (self.frame_sequencer_step + 1) % 8;
+    }
+
+    fn clock_sweep(&mut self) {
+        let sweep = self.registers[(0xFF10 - APU_REGISTERS_BEGIN) as usize];
+        let period = (sweep >> 4) & 0x07;
+
+        if !self.square1.sweep_enabled || period == 0 {
+            return;
+        }
+
+        if self.square1.sweep_timer > 0 {
+            self.square1.sweep_timer -= 1;
+
+            if self.square1.sweep_timer == 0 {
+                self.square1.sweep_timer = period;
+
+                let shift = sweep & 0x07;
+                let decrease = sweep & 0x08 != 0;
+                let delta = self.square1.shadow_frequency >> shift;
+
+                let new_frequency = if decrease {
+                    self.square1.shadow_frequency.saturating_sub(delta)
+                } else {
+                    self.square1.shadow_frequency.saturating_add(delta)
+                };
+
+                if new_frequency > 2047 {
+                    self.square1.enabled = false;
+                } else if shift != 0 {
+                    self.square1.shadow_frequency = new_frequency;
+                    self.registers[(0xFF13 - APU_REGISTERS_BEGIN) as usize] =
+                        (new_frequency & 0xFF) as u8;
+                    let hi = &mut self.registers[(0xFF14 - APU_REGISTERS_BEGIN) as usize];
+                    *hi = (*hi & 0xF8) | ((new_frequency >> 8) as u8 & 0x07);
+                }
+            }
+        }
+    }
+
+    fn mix_sample(&mut self) {
+        let channels = self.square1.amplitude()
+            + self.square2.amplitude()
+            + self.wave.amplitude(&self.registers)
+            + self.noise.amplitude();
+
+        let mixed = channels / 4.0;
+        let filtered = self.filter.process(mixed);
+
+        self.sample_buffer.push(filtered);
+    }
+
+    /// Drains buffered samples for the host audio device. Returns an empty
+    /// vector until enough samples have accumulated, so the first frames
+    /// after startup don't underrun the device.
+    pub fn take_samples(&mut self) -> Vec<f32> {
+        if self.sample_buffer.len() < MIN_BUFFERED_SAMPLES {
+            return Vec::new();
+        }
+
+        std::mem::take(&mut self.sample_buffer)
+    }
+
+    /// Raw NR1x-NR5x/wave-RAM register bytes, for serializing into a save
+    /// state.
+    pub fn registers_snapshot(&self) -> [u8; REGISTER_COUNT] {
+        self.registers
+    }
+
+    /// Restores APU registers from a save state, replaying every byte
+    /// through `write_byte` so a channel that was mid-trigger re-triggers
+    /// with the restored settings.
+    pub fn restore_registers(&mut self, registers: &[u8]) {
+        for (offset, &value) in registers.iter().enumerate() {
+            self.write_byte(APU_REGISTERS_BEGIN + offset as u16, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn register_index(address: u16) -> usize {
+        (address - APU_REGISTERS_BEGIN) as usize
+    }
+
+    #[test]
+    fn square1_trigger_reads_nr10_nr11_nr12() {
+        let mut registers = [0u8; REGISTER_COUNT];
+        registers[register_index(0xFF10)] = 0b0111_0011; // NR10: sweep period 7, shift 3
+        registers[register_index(0xFF11)] = 0b1000_0000; // NR11: duty 2
+        registers[register_index(0xFF12)] = 0b1010_0100; // NR12: volume 10, decrease, period 4
+
+        let mut square1 = SquareChannel {
+            with_sweep: true,
+            ..SquareChannel::default()
+        };
+        square1.trigger(&registers, 0xFF13);
+
+        assert_eq!(square1.volume, 10);
+        assert_eq!(square1.envelope_timer, 4);
+        assert_eq!(square1.duty, 2);
+        assert_eq!(square1.sweep_timer, 7);
+        assert!(square1.sweep_enabled);
+    }
+
+    #[test]
+    fn square2_trigger_reads_nr21_nr22() {
+        let mut registers = [0u8; REGISTER_COUNT];
+        registers[register_index(0xFF16)] = 0b0100_0000; // NR21: duty 1
+        registers[register_index(0xFF17)] = 0b0101_0011; // NR22: volume 5, decrease, period 3
+
+        let mut square2 = SquareChannel::default();
+        square2.trigger(&registers, 0xFF18);
+
+        assert_eq!(square2.volume, 5);
+        assert_eq!(square2.envelope_timer, 3);
+        assert_eq!(square2.duty, 1);
+    }
+
+    #[test]
+    fn square_channel_step_reloads_from_the_live_frequency() {
+        let mut registers = [0u8; REGISTER_COUNT];
+        registers[register_index(0xFF13)] = 0x00; // NR13: frequency lo
+        registers[register_index(0xFF14)] = 0x00; // NR14: frequency hi = 0 -> frequency 0
+
+        let mut square = SquareChannel::default();
+        square.trigger(&registers, 0xFF13);
+
+        // Raise the frequency after triggering, as a game would by writing
+        // NR13/NR14 again without re-triggering.
+        registers[register_index(0xFF13)] = 0x00;
+        registers[register_index(0xFF14)] = 0x07; // frequency 0x700 = 1792
+
+        let expected_reload = (2048 - 1792) * 4;
+
+        square.frequency_timer = 1;
+        square.step(&registers, 0xFF13, 1);
+
+        assert_eq!(square.frequency_timer, expected_reload);
+    }
+
+    #[test]
+    fn wave_channel_step_reloads_from_the_live_frequency() {
+        let mut registers = [0u8; REGISTER_COUNT];
+        registers[register_index(0xFF1A)] = 0x80; // NR30: DAC on
+        registers[register_index(0xFF1D)] = 0x00; // NR33: frequency lo
+        registers[register_index(0xFF1E)] = 0x00; // NR34: frequency hi = 0 -> frequency 0
+
+        let mut wave = WaveChannel::default();
+        wave.trigger(&registers);
+
+        registers[register_index(0xFF1D)] = 0x00;
+        registers[register_index(0xFF1E)] = 0x07; // frequency 0x700 = 1792
+
+        let expected_reload = (2048 - 1792) * 2;
+
+        wave.frequency_timer = 1;
+        wave.step(&registers, 1);
+
+        assert_eq!(wave.frequency_timer, expected_reload);
+    }
+}
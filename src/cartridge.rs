@@ -0,0 +1,295 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const TITLE_BEGIN: usize = 0x134;
+const TITLE_END: usize = 0x143;
+const CARTRIDGE_TYPE_ADDRESS: usize = 0x147;
+const RAM_SIZE_ADDRESS: usize = 0x149;
+
+const ROM_BANK_SIZE: usize = 0x4000;
+const RAM_BANK_SIZE: usize = 0x2000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MemoryBankController {
+    None,
+    MBC1,
+    MBC3,
+}
+
+/// A loaded `.gb` ROM image plus its external RAM, addressed through
+/// whatever memory-bank-controller the cartridge header declares.
+pub struct Cartridge {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    mbc: MemoryBankController,
+    has_battery: bool,
+    rom_bank: usize,
+    ram_bank: usize,
+    ram_enabled: bool,
+    banking_mode: bool,
+    sav_path: Option<PathBuf>,
+}
+
+impl Cartridge {
+    /// Loads a ROM file from disk, parsing the 0x0100-0x014F header to pick
+    /// a bank controller and sizing the external RAM. If the cartridge is
+    /// battery-backed and a sibling `.sav` file exists, it is loaded as the
+    /// initial RAM contents.
+    pub fn load(rom_path: impl AsRef<Path>) -> std::io::Result<Cartridge> {
+        let rom_path = rom_path.as_ref();
+        let rom = fs::read(rom_path)?;
+
+        Ok(Cartridge::from_bytes(rom, Some(rom_path.with_extension("sav"))))
+    }
+
+    /// Builds a cartridge from an already-loaded ROM image, optionally
+    /// tied to a `.sav` path for battery-backed persistence.
+    pub fn from_bytes(rom: Vec<u8>, sav_path: Option<PathBuf>) -> Cartridge {
+        let cartridge_type = *rom.get(CARTRIDGE_TYPE_ADDRESS).unwrap_or(&0);
+        let (mbc, has_battery) = decode_cartridge_type(cartridge_type);
+        let ram_size = decode_ram_size(*rom.get(RAM_SIZE_ADDRESS).unwrap_or(&0));
+
+        let mut ram = vec![0; ram_size];
+
+        if has_battery {
+            if let Some(path) = &sav_path {
+                if let Ok(saved) = fs::read(path) {
+                    let len = saved.len().min(ram.len());
+                    ram[..len].copy_from_slice(&saved[..len]);
+                }
+            }
+        }
+
+        Cartridge {
+            rom,
+            ram,
+            mbc,
+            has_battery,
+            rom_bank: 1,
+            ram_bank: 0,
+            ram_enabled: false,
+            banking_mode: false,
+            sav_path,
+        }
+    }
+
+    /// The game title stored at 0x134-0x143 of the header.
+    pub fn title(&self) -> String {
+        self.rom
+            .get(TITLE_BEGIN..=TITLE_END)
+            .unwrap_or(&[])
+            .iter()
+            .take_while(|&&byte| byte != 0)
+            .map(|&byte| byte as char)
+            .collect()
+    }
+
+    pub fn read_rom(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => self.rom[address as usize],
+            0x4000..=0x7FFF => {
+                let offset = self.rom_bank * ROM_BANK_SIZE + (address as usize - 0x4000);
+                *self.rom.get(offset).unwrap_or(&0xFF)
+            }
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_rom(&mut self, address: u16, value: u8) {
+        match self.mbc {
+            MemoryBankController::None => {}
+            MemoryBankController::MBC1 => self.write_mbc1(address, value),
+            MemoryBankController::MBC3 => self.write_mbc3(address, value),
+        }
+    }
+
+    pub fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return 0xFF;
+        }
+
+        let offset = self.ram_bank * RAM_BANK_SIZE + (address as usize - 0xA000);
+        *self.ram.get(offset).unwrap_or(&0xFF)
+    }
+
+    pub fn write_ram(&mut self, address: u16, value: u8) {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return;
+        }
+
+        let offset = self.ram_bank * RAM_BANK_SIZE + (address as usize - 0xA000);
+
+        if let Some(byte) = self.ram.get_mut(offset) {
+            *byte = value;
+        }
+    }
+
+    fn write_mbc1(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                let bank = (value & 0x1F) as usize;
+                let bank = if bank == 0 { 1 } else { bank };
+                self.rom_bank = (self.rom_bank & !0x1F) | bank;
+            }
+            0x4000..=0x5FFF => {
+                let bits = (value & 0x03) as usize;
+                if self.banking_mode {
+                    self.ram_bank = bits;
+                } else {
+                    self.rom_bank = (self.rom_bank & 0x1F) | (bits << 5);
+                }
+            }
+            0x6000..=0x7FFF => self.banking_mode = value & 0x01 != 0,
+            _ => {}
+        }
+    }
+
+    fn write_mbc3(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                let bank = (value & 0x7F) as usize;
+                self.rom_bank = if bank == 0 { 1 } else { bank };
+            }
+            0x4000..=0x5FFF => self.ram_bank = (value & 0x03) as usize,
+            _ => {}
+        }
+    }
+
+    /// Serializes external RAM and the bank-controller's selector state for
+    /// a whole-machine save state.
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.ram.len() + 13);
+
+        buf.extend_from_slice(&(self.ram.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&self.ram);
+        buf.extend_from_slice(&(self.rom_bank as u32).to_le_bytes());
+        buf.extend_from_slice(&(self.ram_bank as u32).to_le_bytes());
+        buf.push(self.ram_enabled as u8);
+        buf.push(self.banking_mode as u8);
+
+        buf
+    }
+
+    /// Restores external RAM and bank-controller selector state previously
+    /// produced by [`Cartridge::snapshot`]. Errors instead of panicking if
+    /// `bytes` is truncated or otherwise too short, since this is reached
+    /// from loading an arbitrary `.state` file off disk.
+    pub fn restore(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        fn truncated() -> std::io::Error {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "cartridge save state is truncated",
+            )
+        }
+
+        if bytes.len() < 4 {
+            return Err(truncated());
+        }
+        let ram_len = u32::from_le_bytes(bytes[0..4].try_into().unwrap()) as usize;
+        let mut cursor = 4;
+
+        if bytes.len() < cursor + ram_len + 10 {
+            return Err(truncated());
+        }
+
+        self.ram = bytes[cursor..cursor + ram_len].to_vec();
+        cursor += ram_len;
+
+        self.rom_bank = u32::from_le_bytes(bytes[cursor..cursor + 4].try_into().unwrap()) as usize;
+        cursor += 4;
+        self.ram_bank = u32::from_le_bytes(bytes[cursor..cursor + 4].try_into().unwrap()) as usize;
+        cursor += 4;
+        self.ram_enabled = bytes[cursor] != 0;
+        self.banking_mode = bytes[cursor + 1] != 0;
+
+        Ok(())
+    }
+
+    /// Writes external RAM out to the sibling `.sav` file, if this
+    /// cartridge is battery-backed.
+    pub fn save_ram(&self) -> std::io::Result<()> {
+        if !self.has_battery {
+            return Ok(());
+        }
+
+        if let Some(path) = &self.sav_path {
+            fs::write(path, &self.ram)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Cartridge {
+    fn drop(&mut self) {
+        let _ = self.save_ram();
+    }
+}
+
+fn decode_cartridge_type(cartridge_type: u8) -> (MemoryBankController, bool) {
+    use MemoryBankController::*;
+
+    match cartridge_type {
+        0x00 => (None, false),
+        0x01..=0x02 => (MBC1, false),
+        0x03 => (MBC1, true),
+        0x0F..=0x12 => (MBC3, cartridge_type == 0x0F || cartridge_type == 0x10),
+        0x13 => (MBC3, true),
+        _ => (None, false),
+    }
+}
+
+fn decode_ram_size(ram_size_byte: u8) -> usize {
+    match ram_size_byte {
+        0x00 => 0,
+        0x01 => 0x800,
+        0x02 => 0x2000,
+        0x03 => 0x8000,
+        0x04 => 0x20000,
+        0x05 => 0x10000,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rom_with_header() -> Vec<u8> {
+        let mut rom = vec![0u8; 0x8000];
+        rom[0x147] = 0x00; // cartridge type: ROM only
+        rom[0x149] = 0x00; // RAM size: none
+        rom
+    }
+
+    #[test]
+    fn snapshot_round_trips() {
+        let mut cartridge = Cartridge::from_bytes(rom_with_header(), None);
+        cartridge.rom_bank = 3;
+
+        let snapshot = cartridge.snapshot();
+
+        let mut restored = Cartridge::from_bytes(rom_with_header(), None);
+        restored.restore(&snapshot).unwrap();
+
+        assert_eq!(restored.rom_bank, 3);
+    }
+
+    #[test]
+    fn restore_rejects_a_truncated_ram_length_prefix() {
+        let mut cartridge = Cartridge::from_bytes(rom_with_header(), None);
+
+        assert!(cartridge.restore(&[0x00, 0x00]).is_err());
+    }
+
+    #[test]
+    fn restore_rejects_a_truncated_body() {
+        let mut cartridge = Cartridge::from_bytes(rom_with_header(), None);
+        let mut snapshot = cartridge.snapshot();
+        snapshot.truncate(snapshot.len() - 1);
+
+        assert!(cartridge.restore(&snapshot).is_err());
+    }
+}
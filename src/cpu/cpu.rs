@@ -1,8 +1,18 @@
-use crate::{get_lsb, get_msb, get_u16, MemoryBus};
+use crate::{
+    get_lsb, get_msb, get_u16, MemoryBus, JOYPAD_INTERRUPT_BIT, LCD_STAT_INTERRUPT_BIT,
+    SERIAL_INTERRUPT_BIT, TIMER_INTERRUPT_BIT, VBLANK_INTERRUPT_BIT,
+};
+
+const VBLANK_VECTOR: u16 = 0x40;
+const LCD_STAT_VECTOR: u16 = 0x48;
+const TIMER_VECTOR: u16 = 0x50;
+const SERIAL_VECTOR: u16 = 0x58;
+const JOYPAD_VECTOR: u16 = 0x60;
 
 use super::{
-    ArithmeticTarget, FlagsRegister, Instruction, LoadByteSource, LoadByteTarget, LoadType,
-    PopTarget, PushSource, Registers,
+    ArithmeticTarget, FlagsRegister, IncDecTarget, Instruction, JumpType, LoadAFromSource,
+    LoadByteSource, LoadByteTarget, LoadTargetFromA, LoadType, PopTarget, PrefixTarget, PushSource,
+    Registers, WordRegister,
 };
 
 pub struct CPU {
@@ -11,9 +21,78 @@ pub struct CPU {
     sp: u16,
     bus: MemoryBus,
     is_halted: bool,
+    cycles: u64,
+    ime: bool,
+    /// Countdown until a pending `EI` takes effect: 0 means no `EI` is
+    /// pending, otherwise it's decremented once per `step_cycles` call and
+    /// `ime` is set the step it reaches 0, so `EI` only starts allowing
+    /// interrupts before the *second* instruction after it is fetched.
+    ime_enable_delay: u8,
 }
 
 impl CPU {
+    /// Builds a CPU in the register/`pc`/`sp` state the DMG boot ROM leaves
+    /// behind, so emulation can start at the cartridge's entry point
+    /// (`0x0100`) without actually running a boot ROM image.
+    pub fn new(bus: MemoryBus) -> CPU {
+        CPU {
+            registers: Registers {
+                a: 0x01,
+                b: 0x00,
+                c: 0x13,
+                d: 0x00,
+                e: 0xD8,
+                f: FlagsRegister::from(0xB0),
+                h: 0x01,
+                l: 0x4D,
+            },
+            pc: 0x0100,
+            sp: 0xFFFE,
+            bus,
+            is_halted: false,
+            cycles: 0,
+            ime: false,
+            ime_enable_delay: 0,
+        }
+    }
+
+    pub fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn sp(&self) -> u16 {
+        self.sp
+    }
+
+    pub fn bus(&self) -> &MemoryBus {
+        &self.bus
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.is_halted
+    }
+
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Decodes the instruction at `pc` without executing it, for a
+    /// disassembler to format.
+    pub fn peek_next_instruction(&self) -> Instruction {
+        let mut instruction_byte = self.bus.read_byte(self.pc);
+        let prefixed = instruction_byte == 0xCB;
+
+        if prefixed {
+            instruction_byte = self.bus.read_byte(self.pc + 1);
+        }
+
+        self.decode(instruction_byte, prefixed)
+    }
+
     fn read_next_byte(&self) -> u8 {
         self.bus.read_byte(self.pc + 1)
     }
@@ -38,7 +117,29 @@ impl CPU {
         }
     }
 
-    fn step(&mut self) {
+    /// Decodes and executes the instruction at `pc`, returning the number of
+    /// machine cycles (M-cycles) it consumed so a host loop can pace other
+    /// hardware (GPU, timers, APU) against it.
+    pub fn step_cycles(&mut self) -> u8 {
+        if self.ime_enable_delay > 0 {
+            self.ime_enable_delay -= 1;
+            if self.ime_enable_delay == 0 {
+                self.ime = true;
+            }
+        }
+
+        if let Some(cycles) = self.service_interrupt() {
+            self.cycles = self.cycles.wrapping_add(cycles as u64);
+            self.bus.tick(cycles);
+            return cycles;
+        }
+
+        if self.is_halted {
+            self.cycles = self.cycles.wrapping_add(1);
+            self.bus.tick(1);
+            return 1;
+        }
+
         let mut instruction_byte = self.bus.read_byte(self.pc);
         let prefixed = instruction_byte == 0xCB;
 
@@ -47,84 +148,365 @@ impl CPU {
         }
 
         let instruction = self.decode(instruction_byte, prefixed);
+        let (next_pc, cycles) = self.execute(instruction);
+        self.pc = next_pc;
 
-        self.pc = self.execute(instruction);
+        self.cycles = self.cycles.wrapping_add(cycles as u64);
+        self.bus.tick(cycles);
+
+        cycles
     }
 
-    fn execute(&mut self, instruction: Instruction) -> u16 {
-        use Instruction::*;
-        use LoadType::*;
+    /// Pending-interrupt bits common to `IE & IF`, in priority order.
+    fn pending_interrupts(&self) -> u8 {
+        self.bus.interrupt_enable & self.bus.interrupt_flag & 0x1F
+    }
+
+    /// Runs the interrupt service routine if one is pending and enabled,
+    /// returning the cycles it consumed. `HALT` is woken by any pending
+    /// interrupt even while `IME` is disabled.
+    fn service_interrupt(&mut self) -> Option<u8> {
+        let pending = self.pending_interrupts();
+
+        if pending == 0 {
+            return None;
+        }
 
         if self.is_halted {
-            return self.pc;
+            self.is_halted = false;
         }
 
-        match instruction {
-            ADD(target) => match target {
-                ArithmeticTarget::C => {
-                    let value = self.registers.c;
-                    let new_value = self.add(value);
+        if !self.ime {
+            return None;
+        }
 
-                    self.registers.a = new_value;
-                    self.pc.wrapping_add(1)
-                }
-                _ => self.pc,
-            },
-            JP(jump_type) => self.jump(jump_type.should_jump(&self.registers.f)),
-            LD(load_type) => match load_type {
-                Byte(target, source) => {
-                    let source_value = match source {
-                        LoadByteSource::A => self.registers.a,
-                        LoadByteSource::B => self.registers.b,
-                        LoadByteSource::C => self.registers.c,
-                        LoadByteSource::D => self.registers.d,
-                        LoadByteSource::E => self.registers.e,
-                        LoadByteSource::H => self.registers.h,
-                        LoadByteSource::L => self.registers.l,
-                        LoadByteSource::D8 => self.read_next_byte(),
-                        LoadByteSource::HLI => self.bus.read_byte(self.registers.get_hl()),
-                    };
-
-                    match target {
-                        LoadByteTarget::A => self.registers.a = source_value,
-                        LoadByteTarget::B => self.registers.b = source_value,
-                        LoadByteTarget::C => self.registers.c = source_value,
-                        LoadByteTarget::D => self.registers.d = source_value,
-                        LoadByteTarget::E => self.registers.e = source_value,
-                        LoadByteTarget::H => self.registers.h = source_value,
-                        LoadByteTarget::L => self.registers.l = source_value,
-                        LoadByteTarget::HLI => {
-                            self.bus.write_byte(self.registers.get_hl(), source_value)
-                        }
-                    }
+        let (bit, vector) = if pending & (1 << VBLANK_INTERRUPT_BIT) != 0 {
+            (VBLANK_INTERRUPT_BIT, VBLANK_VECTOR)
+        } else if pending & (1 << LCD_STAT_INTERRUPT_BIT) != 0 {
+            (LCD_STAT_INTERRUPT_BIT, LCD_STAT_VECTOR)
+        } else if pending & (1 << TIMER_INTERRUPT_BIT) != 0 {
+            (TIMER_INTERRUPT_BIT, TIMER_VECTOR)
+        } else if pending & (1 << SERIAL_INTERRUPT_BIT) != 0 {
+            (SERIAL_INTERRUPT_BIT, SERIAL_VECTOR)
+        } else {
+            (JOYPAD_INTERRUPT_BIT, JOYPAD_VECTOR)
+        };
 
-                    match source {
-                        LoadByteSource::D8 => self.pc.wrapping_add(2),
-                        _ => self.pc.wrapping_add(1),
-                    }
-                }
-                // FromA(target) => {
-                //     match target {
-                //         LoadTargetFromA::BC => self.registers.set_bc(self.registers.a as u16),
-                //         LoadTargetFromA::DE => self.registers.set_de(self.registers.a as u16),
-                //         LoadTargetFromA::D16 => {
-                //             let least_significant_byte = self.bus.read_byte(self.pc + 1) as u16;
-                //             let most_significant_byte = self.bus.read_byte(self.pc + 2) as u16;
-                //             let address = get_16b_n(most_significant_byte, least_significant_byte);
-
-                //             self.bus.write_byte(address, self.registers.a);
-                //         }
-                //     }
-
-                //     match target {
-                //         LoadTargetFromA::D16 => self.pc.wrapping_add(3),
-                //         _ => self.pc.wrapping_add(1)
-                //     }
-                // },
-                _ => {
-                    todo!("Implement rest types")
-                }
+        self.ime = false;
+        self.ime_enable_delay = 0;
+        self.bus.interrupt_flag &= !(1 << bit);
+        self.push(self.pc);
+        self.pc = vector;
+
+        Some(5)
+    }
+
+    /// M-cycle cost of a `LD` variant, which (unlike the ALU/prefixed ops)
+    /// has no uniform register-vs-`(HL)`-vs-immediate shape to generalize.
+    fn load_cycles(load_type: &LoadType) -> u8 {
+        use LoadType::*;
+
+        match load_type {
+            Byte(target, source) => match (target, source) {
+                (LoadByteTarget::HLI, LoadByteSource::D8) => 3,
+                (LoadByteTarget::HLI, _)
+                | (LoadByteTarget::HLINC, _)
+                | (LoadByteTarget::HLDEC, _) => 2,
+                (_, LoadByteSource::HLI | LoadByteSource::HLINC | LoadByteSource::HLDEC) => 2,
+                (_, LoadByteSource::D8) => 2,
+                _ => 1,
             },
+            Word(_) => 3,
+            FromA(LoadTargetFromA::A8) => 3,
+            FromA(LoadTargetFromA::D16) => 4,
+            FromA(_) => 2,
+            ToA(LoadAFromSource::A8) => 3,
+            ToA(LoadAFromSource::D16) => 4,
+            ToA(_) => 2,
+            SPFromHL => 2,
+            HLFromSPOffset => 3,
+            IndirectFromSP => 5,
+        }
+    }
+
+    fn inc_dec_cycles(target: &IncDecTarget) -> u8 {
+        match target {
+            IncDecTarget::HLI => 3,
+            _ => 1,
+        }
+    }
+
+    fn prefix_op_cycles(target: &PrefixTarget) -> u8 {
+        match target {
+            PrefixTarget::HLI => 4,
+            _ => 2,
+        }
+    }
+
+    fn bit_cycles(target: &PrefixTarget) -> u8 {
+        match target {
+            PrefixTarget::HLI => 3,
+            _ => 2,
+        }
+    }
+
+    fn read_arithmetic_target(&mut self, target: &ArithmeticTarget) -> u8 {
+        match target {
+            ArithmeticTarget::A => self.registers.a,
+            ArithmeticTarget::B => self.registers.b,
+            ArithmeticTarget::C => self.registers.c,
+            ArithmeticTarget::D => self.registers.d,
+            ArithmeticTarget::E => self.registers.e,
+            ArithmeticTarget::H => self.registers.h,
+            ArithmeticTarget::L => self.registers.l,
+            ArithmeticTarget::HLI => self.bus.read_byte(self.registers.get_hl()),
+            ArithmeticTarget::D8 => self.read_next_byte(),
+        }
+    }
+
+    fn arithmetic_target_width(target: &ArithmeticTarget) -> u16 {
+        match target {
+            ArithmeticTarget::HLI | ArithmeticTarget::D8 => 2,
+            _ => 1,
+        }
+    }
+
+    fn read_prefix_target(&self, target: &PrefixTarget) -> u8 {
+        match target {
+            PrefixTarget::A => self.registers.a,
+            PrefixTarget::B => self.registers.b,
+            PrefixTarget::C => self.registers.c,
+            PrefixTarget::D => self.registers.d,
+            PrefixTarget::E => self.registers.e,
+            PrefixTarget::H => self.registers.h,
+            PrefixTarget::L => self.registers.l,
+            PrefixTarget::HLI => self.bus.read_byte(self.registers.get_hl()),
+        }
+    }
+
+    fn write_prefix_target(&mut self, target: &PrefixTarget, value: u8) {
+        match target {
+            PrefixTarget::A => self.registers.a = value,
+            PrefixTarget::B => self.registers.b = value,
+            PrefixTarget::C => self.registers.c = value,
+            PrefixTarget::D => self.registers.d = value,
+            PrefixTarget::E => self.registers.e = value,
+            PrefixTarget::H => self.registers.h = value,
+            PrefixTarget::L => self.registers.l = value,
+            PrefixTarget::HLI => self.bus.write_byte(self.registers.get_hl(), value),
+        }
+    }
+
+    fn read_word_register(&self, target: &WordRegister) -> u16 {
+        match target {
+            WordRegister::BC => self.registers.get_bc(),
+            WordRegister::DE => self.registers.get_de(),
+            WordRegister::HL => self.registers.get_hl(),
+            WordRegister::SP => self.sp,
+        }
+    }
+
+    fn write_word_register(&mut self, target: &WordRegister, value: u16) {
+        match target {
+            WordRegister::BC => self.registers.set_bc(value),
+            WordRegister::DE => self.registers.set_de(value),
+            WordRegister::HL => self.registers.set_hl(value),
+            WordRegister::SP => self.sp = value,
+        }
+    }
+
+    /// Runs the already-decoded `instruction`, returning the next `pc` and the
+    /// number of M-cycles it consumed.
+    fn execute(&mut self, instruction: Instruction) -> (u16, u8) {
+        use Instruction::*;
+
+        if self.is_halted {
+            return (self.pc, 1);
+        }
+
+        match instruction {
+            ADD(target) => {
+                let value = self.read_arithmetic_target(&target);
+                let width = Self::arithmetic_target_width(&target);
+                self.registers.a = self.add(value);
+                (self.pc.wrapping_add(width), width as u8)
+            }
+            ADC(target) => {
+                let value = self.read_arithmetic_target(&target);
+                let width = Self::arithmetic_target_width(&target);
+                self.registers.a = self.adc(value);
+                (self.pc.wrapping_add(width), width as u8)
+            }
+            SUB(target) => {
+                let value = self.read_arithmetic_target(&target);
+                let width = Self::arithmetic_target_width(&target);
+                self.registers.a = self.sub(value);
+                (self.pc.wrapping_add(width), width as u8)
+            }
+            SBC(target) => {
+                let value = self.read_arithmetic_target(&target);
+                let width = Self::arithmetic_target_width(&target);
+                self.registers.a = self.sbc(value);
+                (self.pc.wrapping_add(width), width as u8)
+            }
+            AND(target) => {
+                let value = self.read_arithmetic_target(&target);
+                let width = Self::arithmetic_target_width(&target);
+                self.registers.a = self.and(value);
+                (self.pc.wrapping_add(width), width as u8)
+            }
+            OR(target) => {
+                let value = self.read_arithmetic_target(&target);
+                let width = Self::arithmetic_target_width(&target);
+                self.registers.a = self.or(value);
+                (self.pc.wrapping_add(width), width as u8)
+            }
+            XOR(target) => {
+                let value = self.read_arithmetic_target(&target);
+                let width = Self::arithmetic_target_width(&target);
+                self.registers.a = self.xor(value);
+                (self.pc.wrapping_add(width), width as u8)
+            }
+            CP(target) => {
+                let value = self.read_arithmetic_target(&target);
+                let width = Self::arithmetic_target_width(&target);
+                self.cp(value);
+                (self.pc.wrapping_add(width), width as u8)
+            }
+            INC(target) => {
+                let cycles = Self::inc_dec_cycles(&target);
+                self.inc(target);
+                (self.pc.wrapping_add(1), cycles)
+            }
+            DEC(target) => {
+                let cycles = Self::inc_dec_cycles(&target);
+                self.dec(target);
+                (self.pc.wrapping_add(1), cycles)
+            }
+            INC16(target) => {
+                let value = self.read_word_register(&target).wrapping_add(1);
+                self.write_word_register(&target, value);
+                (self.pc.wrapping_add(1), 2)
+            }
+            DEC16(target) => {
+                let value = self.read_word_register(&target).wrapping_sub(1);
+                self.write_word_register(&target, value);
+                (self.pc.wrapping_add(1), 2)
+            }
+            ADDHL(target) => {
+                let value = self.read_word_register(&target);
+                let hl = self.add_hl(value);
+                self.registers.set_hl(hl);
+                (self.pc.wrapping_add(1), 2)
+            }
+            ADDSP => {
+                self.sp = self.add_sp();
+                (self.pc.wrapping_add(2), 4)
+            }
+            RLCA => {
+                self.registers.a = self.rlc(self.registers.a);
+                self.registers.f.zero = false;
+                (self.pc.wrapping_add(1), 1)
+            }
+            RRCA => {
+                self.registers.a = self.rrc(self.registers.a);
+                self.registers.f.zero = false;
+                (self.pc.wrapping_add(1), 1)
+            }
+            RLA => {
+                self.registers.a = self.rl(self.registers.a);
+                self.registers.f.zero = false;
+                (self.pc.wrapping_add(1), 1)
+            }
+            RRA => {
+                self.registers.a = self.rr(self.registers.a);
+                self.registers.f.zero = false;
+                (self.pc.wrapping_add(1), 1)
+            }
+            RLC(target) => {
+                let cycles = Self::prefix_op_cycles(&target);
+                let value = self.read_prefix_target(&target);
+                let new_value = self.rlc(value);
+                self.write_prefix_target(&target, new_value);
+                (self.pc.wrapping_add(2), cycles)
+            }
+            RRC(target) => {
+                let cycles = Self::prefix_op_cycles(&target);
+                let value = self.read_prefix_target(&target);
+                let new_value = self.rrc(value);
+                self.write_prefix_target(&target, new_value);
+                (self.pc.wrapping_add(2), cycles)
+            }
+            RL(target) => {
+                let cycles = Self::prefix_op_cycles(&target);
+                let value = self.read_prefix_target(&target);
+                let new_value = self.rl(value);
+                self.write_prefix_target(&target, new_value);
+                (self.pc.wrapping_add(2), cycles)
+            }
+            RR(target) => {
+                let cycles = Self::prefix_op_cycles(&target);
+                let value = self.read_prefix_target(&target);
+                let new_value = self.rr(value);
+                self.write_prefix_target(&target, new_value);
+                (self.pc.wrapping_add(2), cycles)
+            }
+            SLA(target) => {
+                let cycles = Self::prefix_op_cycles(&target);
+                let value = self.read_prefix_target(&target);
+                let new_value = self.sla(value);
+                self.write_prefix_target(&target, new_value);
+                (self.pc.wrapping_add(2), cycles)
+            }
+            SRA(target) => {
+                let cycles = Self::prefix_op_cycles(&target);
+                let value = self.read_prefix_target(&target);
+                let new_value = self.sra(value);
+                self.write_prefix_target(&target, new_value);
+                (self.pc.wrapping_add(2), cycles)
+            }
+            SWAP(target) => {
+                let cycles = Self::prefix_op_cycles(&target);
+                let value = self.read_prefix_target(&target);
+                let new_value = self.swap(value);
+                self.write_prefix_target(&target, new_value);
+                (self.pc.wrapping_add(2), cycles)
+            }
+            SRL(target) => {
+                let cycles = Self::prefix_op_cycles(&target);
+                let value = self.read_prefix_target(&target);
+                let new_value = self.srl(value);
+                self.write_prefix_target(&target, new_value);
+                (self.pc.wrapping_add(2), cycles)
+            }
+            BIT(bit, target) => {
+                let cycles = Self::bit_cycles(&target);
+                let value = self.read_prefix_target(&target);
+                self.registers.f.zero = value & (1 << bit) == 0;
+                self.registers.f.subtract = false;
+                self.registers.f.half_carry = true;
+                (self.pc.wrapping_add(2), cycles)
+            }
+            SET(bit, target) => {
+                let cycles = Self::prefix_op_cycles(&target);
+                let value = self.read_prefix_target(&target);
+                self.write_prefix_target(&target, value | (1 << bit));
+                (self.pc.wrapping_add(2), cycles)
+            }
+            RES(bit, target) => {
+                let cycles = Self::prefix_op_cycles(&target);
+                let value = self.read_prefix_target(&target);
+                self.write_prefix_target(&target, value & !(1 << bit));
+                (self.pc.wrapping_add(2), cycles)
+            }
+            JP(jump_type) => self.jump(&jump_type),
+            JPI => (self.registers.get_hl(), 1),
+            JR(jump_type) => self.jump_relative(&jump_type),
+            LD(load_type) => {
+                let cycles = Self::load_cycles(&load_type);
+                let pc = self.execute_load(load_type);
+                (pc, cycles)
+            }
             PUSH(source) => {
                 let source_value = match source {
                     PushSource::AF => {
@@ -140,7 +522,7 @@ impl CPU {
 
                 self.push(source_value);
 
-                self.pc.wrapping_add(1)
+                (self.pc.wrapping_add(1), 4)
             }
             POP(target) => {
                 let value = self.pop();
@@ -151,36 +533,198 @@ impl CPU {
                         let most_significant_byte = get_msb(&value);
 
                         self.registers.a = most_significant_byte;
-                        self.registers.f = FlagsRegister::from(least_significant_byte);
+                        self.registers.f = FlagsRegister::from(least_significant_byte & 0xF0);
                     }
                     PopTarget::BC => self.registers.set_bc(value),
                     PopTarget::DE => self.registers.set_de(value),
                     PopTarget::HL => self.registers.set_hl(value),
                 }
 
-                self.pc.wrapping_add(1)
+                (self.pc.wrapping_add(1), 3)
             }
-            CALL(jump_type) => {
-                let should_jump = jump_type.should_jump(&self.registers.f);
-
-                self.call(should_jump)
+            CALL(jump_type) => self.call(&jump_type),
+            RET(jump_type) => self.return_(&jump_type),
+            RETI => {
+                self.ime = true;
+                self.ime_enable_delay = 0;
+                (self.pop(), 4)
             }
-            RET(jump_type) => {
-                let should_jump = jump_type.should_jump(&self.registers.f);
-
-                self.return_(should_jump)
+            RST(vector) => {
+                self.push(self.pc.wrapping_add(1));
+                (vector as u16, 4)
             }
-            NOP => self.pc.wrapping_add(1),
+            NOP => (self.pc.wrapping_add(1), 1),
             HALT => {
                 self.is_halted = true;
-                self.pc
+                (self.pc, 1)
+            }
+            STOP => (self.pc.wrapping_add(2), 1),
+            DAA => {
+                self.daa();
+                (self.pc.wrapping_add(1), 1)
+            }
+            CPL => {
+                self.registers.a = !self.registers.a;
+                self.registers.f.subtract = true;
+                self.registers.f.half_carry = true;
+                (self.pc.wrapping_add(1), 1)
+            }
+            SCF => {
+                self.registers.f.subtract = false;
+                self.registers.f.half_carry = false;
+                self.registers.f.carry = true;
+                (self.pc.wrapping_add(1), 1)
+            }
+            CCF => {
+                self.registers.f.subtract = false;
+                self.registers.f.half_carry = false;
+                self.registers.f.carry = !self.registers.f.carry;
+                (self.pc.wrapping_add(1), 1)
+            }
+            EI => {
+                self.ime_enable_delay = 2;
+                (self.pc.wrapping_add(1), 1)
+            }
+            DI => {
+                self.ime = false;
+                self.ime_enable_delay = 0;
+                (self.pc.wrapping_add(1), 1)
+            }
+        }
+    }
+
+    /// Runs a decoded `LD` instruction and returns the next `pc`; cycle cost
+    /// is charged separately by the caller via `load_cycles`.
+    fn execute_load(&mut self, load_type: LoadType) -> u16 {
+        use LoadType::*;
+
+        match load_type {
+            Byte(target, source) => {
+                let source_value = match source {
+                    LoadByteSource::A => self.registers.a,
+                    LoadByteSource::B => self.registers.b,
+                    LoadByteSource::C => self.registers.c,
+                    LoadByteSource::D => self.registers.d,
+                    LoadByteSource::E => self.registers.e,
+                    LoadByteSource::H => self.registers.h,
+                    LoadByteSource::L => self.registers.l,
+                    LoadByteSource::D8 => self.read_next_byte(),
+                    LoadByteSource::HLI => self.bus.read_byte(self.registers.get_hl()),
+                    LoadByteSource::HLINC => {
+                        let address = self.registers.get_hl();
+                        self.registers.set_hl(address.wrapping_add(1));
+                        self.bus.read_byte(address)
+                    }
+                    LoadByteSource::HLDEC => {
+                        let address = self.registers.get_hl();
+                        self.registers.set_hl(address.wrapping_sub(1));
+                        self.bus.read_byte(address)
+                    }
+                };
+
+                match target {
+                    LoadByteTarget::A => self.registers.a = source_value,
+                    LoadByteTarget::B => self.registers.b = source_value,
+                    LoadByteTarget::C => self.registers.c = source_value,
+                    LoadByteTarget::D => self.registers.d = source_value,
+                    LoadByteTarget::E => self.registers.e = source_value,
+                    LoadByteTarget::H => self.registers.h = source_value,
+                    LoadByteTarget::L => self.registers.l = source_value,
+                    LoadByteTarget::HLI => {
+                        self.bus.write_byte(self.registers.get_hl(), source_value)
+                    }
+                    LoadByteTarget::HLINC => {
+                        let address = self.registers.get_hl();
+                        self.registers.set_hl(address.wrapping_add(1));
+                        self.bus.write_byte(address, source_value)
+                    }
+                    LoadByteTarget::HLDEC => {
+                        let address = self.registers.get_hl();
+                        self.registers.set_hl(address.wrapping_sub(1));
+                        self.bus.write_byte(address, source_value)
+                    }
+                }
+
+                match source {
+                    LoadByteSource::D8 => self.pc.wrapping_add(2),
+                    _ => self.pc.wrapping_add(1),
+                }
             }
-            _ => {
-                todo!("Implement rest instructions")
+            Word(target) => {
+                let value = self.read_next_word();
+                self.write_word_register(&target, value);
+                self.pc.wrapping_add(3)
+            }
+            FromA(target) => {
+                match target {
+                    LoadTargetFromA::BC => self
+                        .bus
+                        .write_byte(self.registers.get_bc(), self.registers.a),
+                    LoadTargetFromA::DE => self
+                        .bus
+                        .write_byte(self.registers.get_de(), self.registers.a),
+                    LoadTargetFromA::C => self
+                        .bus
+                        .write_byte(0xFF00 + self.registers.c as u16, self.registers.a),
+                    LoadTargetFromA::A8 => {
+                        let address = 0xFF00 + self.read_next_byte() as u16;
+                        self.bus.write_byte(address, self.registers.a)
+                    }
+                    LoadTargetFromA::D16 => {
+                        let address = self.read_next_word();
+                        self.bus.write_byte(address, self.registers.a)
+                    }
+                }
+
+                match target {
+                    LoadTargetFromA::D16 => self.pc.wrapping_add(3),
+                    LoadTargetFromA::A8 => self.pc.wrapping_add(2),
+                    _ => self.pc.wrapping_add(1),
+                }
+            }
+            ToA(source) => {
+                self.registers.a = match source {
+                    LoadAFromSource::BC => self.bus.read_byte(self.registers.get_bc()),
+                    LoadAFromSource::DE => self.bus.read_byte(self.registers.get_de()),
+                    LoadAFromSource::C => self.bus.read_byte(0xFF00 + self.registers.c as u16),
+                    LoadAFromSource::A8 => {
+                        let address = 0xFF00 + self.read_next_byte() as u16;
+                        self.bus.read_byte(address)
+                    }
+                    LoadAFromSource::D16 => {
+                        let address = self.read_next_word();
+                        self.bus.read_byte(address)
+                    }
+                };
+
+                match source {
+                    LoadAFromSource::D16 => self.pc.wrapping_add(3),
+                    LoadAFromSource::A8 => self.pc.wrapping_add(2),
+                    _ => self.pc.wrapping_add(1),
+                }
+            }
+            SPFromHL => {
+                self.sp = self.registers.get_hl();
+                self.pc.wrapping_add(1)
+            }
+            HLFromSPOffset => {
+                let result = self.add_sp();
+                self.registers.set_hl(result);
+                self.pc.wrapping_add(2)
+            }
+            IndirectFromSP => {
+                let address = self.read_next_word();
+                self.bus.write_byte(address, get_lsb(&self.sp));
+                self.bus
+                    .write_byte(address.wrapping_add(1), get_msb(&self.sp));
+                self.pc.wrapping_add(3)
             }
         }
     }
 
+    /// 8-bit ALU ops (`ADD`/`ADC`/`SUB`/`SBC`/`AND`/`OR`/`XOR`/`CP`). Each
+    /// sets `registers.f` per the documented flag semantics and returns the
+    /// result; callers write it back to `A` themselves (`CP` discards it).
     fn add(&mut self, value: u8) -> u8 {
         let (new_value, did_overflow) = self.registers.a.overflowing_add(value);
 
@@ -194,11 +738,306 @@ impl CPU {
         new_value
     }
 
-    fn jump(&mut self, should_jump: bool) -> u16 {
-        if should_jump {
-            self.read_next_word()
+    fn adc(&mut self, value: u8) -> u8 {
+        let carry = if self.registers.f.carry { 1 } else { 0 };
+        let new_value = self.registers.a as u16 + value as u16 + carry as u16;
+
+        self.registers.f.zero = (new_value & 0xFF) == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.carry = new_value > 0xFF;
+        self.registers.f.half_carry = (self.registers.a & 0xF) + (value & 0xF) + carry > 0xF;
+
+        new_value as u8
+    }
+
+    fn sub(&mut self, value: u8) -> u8 {
+        let (new_value, did_overflow) = self.registers.a.overflowing_sub(value);
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = true;
+        self.registers.f.carry = did_overflow;
+        self.registers.f.half_carry = (self.registers.a & 0xF) < (value & 0xF);
+
+        new_value
+    }
+
+    fn sbc(&mut self, value: u8) -> u8 {
+        let carry = if self.registers.f.carry { 1 } else { 0 };
+        let (partial, overflow1) = self.registers.a.overflowing_sub(value);
+        let (new_value, overflow2) = partial.overflowing_sub(carry);
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = true;
+        self.registers.f.carry = overflow1 || overflow2;
+        self.registers.f.half_carry = (self.registers.a & 0xF) < (value & 0xF) + carry;
+
+        new_value
+    }
+
+    fn and(&mut self, value: u8) -> u8 {
+        let new_value = self.registers.a & value;
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = true;
+        self.registers.f.carry = false;
+
+        new_value
+    }
+
+    fn or(&mut self, value: u8) -> u8 {
+        let new_value = self.registers.a | value;
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = false;
+        self.registers.f.carry = false;
+
+        new_value
+    }
+
+    fn xor(&mut self, value: u8) -> u8 {
+        let new_value = self.registers.a ^ value;
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = false;
+        self.registers.f.carry = false;
+
+        new_value
+    }
+
+    fn cp(&mut self, value: u8) {
+        self.sub(value);
+    }
+
+    fn inc(&mut self, target: IncDecTarget) {
+        let value = match target {
+            IncDecTarget::A => self.registers.a,
+            IncDecTarget::B => self.registers.b,
+            IncDecTarget::C => self.registers.c,
+            IncDecTarget::D => self.registers.d,
+            IncDecTarget::E => self.registers.e,
+            IncDecTarget::H => self.registers.h,
+            IncDecTarget::L => self.registers.l,
+            IncDecTarget::HLI => self.bus.read_byte(self.registers.get_hl()),
+        };
+
+        let new_value = value.wrapping_add(1);
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = (value & 0xF) + 1 > 0xF;
+
+        match target {
+            IncDecTarget::A => self.registers.a = new_value,
+            IncDecTarget::B => self.registers.b = new_value,
+            IncDecTarget::C => self.registers.c = new_value,
+            IncDecTarget::D => self.registers.d = new_value,
+            IncDecTarget::E => self.registers.e = new_value,
+            IncDecTarget::H => self.registers.h = new_value,
+            IncDecTarget::L => self.registers.l = new_value,
+            IncDecTarget::HLI => self.bus.write_byte(self.registers.get_hl(), new_value),
+        }
+    }
+
+    fn dec(&mut self, target: IncDecTarget) {
+        let value = match target {
+            IncDecTarget::A => self.registers.a,
+            IncDecTarget::B => self.registers.b,
+            IncDecTarget::C => self.registers.c,
+            IncDecTarget::D => self.registers.d,
+            IncDecTarget::E => self.registers.e,
+            IncDecTarget::H => self.registers.h,
+            IncDecTarget::L => self.registers.l,
+            IncDecTarget::HLI => self.bus.read_byte(self.registers.get_hl()),
+        };
+
+        let new_value = value.wrapping_sub(1);
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = true;
+        self.registers.f.half_carry = (value & 0xF) == 0;
+
+        match target {
+            IncDecTarget::A => self.registers.a = new_value,
+            IncDecTarget::B => self.registers.b = new_value,
+            IncDecTarget::C => self.registers.c = new_value,
+            IncDecTarget::D => self.registers.d = new_value,
+            IncDecTarget::E => self.registers.e = new_value,
+            IncDecTarget::H => self.registers.h = new_value,
+            IncDecTarget::L => self.registers.l = new_value,
+            IncDecTarget::HLI => self.bus.write_byte(self.registers.get_hl(), new_value),
+        }
+    }
+
+    fn add_hl(&mut self, value: u16) -> u16 {
+        let hl = self.registers.get_hl();
+        let (new_value, did_overflow) = hl.overflowing_add(value);
+
+        self.registers.f.subtract = false;
+        self.registers.f.carry = did_overflow;
+        self.registers.f.half_carry = (hl & 0xFFF) + (value & 0xFFF) > 0xFFF;
+
+        new_value
+    }
+
+    fn add_sp(&mut self) -> u16 {
+        let value = self.read_next_byte() as i8 as i16 as u16;
+        let sp = self.sp;
+        let new_value = sp.wrapping_add(value);
+
+        self.registers.f.zero = false;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = (sp & 0xF) + (value & 0xF) > 0xF;
+        self.registers.f.carry = (sp & 0xFF) + (value & 0xFF) > 0xFF;
+
+        new_value
+    }
+
+    fn rlc(&mut self, value: u8) -> u8 {
+        let carry = value & 0x80 != 0;
+        let new_value = value.rotate_left(1);
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = false;
+        self.registers.f.carry = carry;
+
+        new_value
+    }
+
+    fn rrc(&mut self, value: u8) -> u8 {
+        let carry = value & 0x01 != 0;
+        let new_value = value.rotate_right(1);
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = false;
+        self.registers.f.carry = carry;
+
+        new_value
+    }
+
+    fn rl(&mut self, value: u8) -> u8 {
+        let old_carry = if self.registers.f.carry { 1 } else { 0 };
+        let carry = value & 0x80 != 0;
+        let new_value = (value << 1) | old_carry;
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = false;
+        self.registers.f.carry = carry;
+
+        new_value
+    }
+
+    fn rr(&mut self, value: u8) -> u8 {
+        let old_carry = if self.registers.f.carry { 0x80 } else { 0 };
+        let carry = value & 0x01 != 0;
+        let new_value = (value >> 1) | old_carry;
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = false;
+        self.registers.f.carry = carry;
+
+        new_value
+    }
+
+    fn sla(&mut self, value: u8) -> u8 {
+        let carry = value & 0x80 != 0;
+        let new_value = value << 1;
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = false;
+        self.registers.f.carry = carry;
+
+        new_value
+    }
+
+    fn sra(&mut self, value: u8) -> u8 {
+        let carry = value & 0x01 != 0;
+        let new_value = (value >> 1) | (value & 0x80);
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = false;
+        self.registers.f.carry = carry;
+
+        new_value
+    }
+
+    fn swap(&mut self, value: u8) -> u8 {
+        let new_value = value.rotate_right(4);
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = false;
+        self.registers.f.carry = false;
+
+        new_value
+    }
+
+    fn srl(&mut self, value: u8) -> u8 {
+        let carry = value & 0x01 != 0;
+        let new_value = value >> 1;
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = false;
+        self.registers.f.carry = carry;
+
+        new_value
+    }
+
+    fn daa(&mut self) {
+        let mut adjust = 0;
+        let mut carry = self.registers.f.carry;
+
+        if self.registers.f.half_carry
+            || (!self.registers.f.subtract && (self.registers.a & 0xF) > 9)
+        {
+            adjust |= 0x06;
+        }
+
+        if self.registers.f.carry || (!self.registers.f.subtract && self.registers.a > 0x99) {
+            adjust |= 0x60;
+            carry = true;
+        }
+
+        if self.registers.f.subtract {
+            self.registers.a = self.registers.a.wrapping_sub(adjust);
+        } else {
+            self.registers.a = self.registers.a.wrapping_add(adjust);
+        }
+
+        self.registers.f.zero = self.registers.a == 0;
+        self.registers.f.half_carry = false;
+        self.registers.f.carry = carry;
+    }
+
+    /// Runs `JP`, returning the next `pc` and the cycles it cost: a taken
+    /// jump reads the target address and costs a cycle more than the
+    /// fall-through.
+    fn jump(&mut self, jump_type: &JumpType) -> (u16, u8) {
+        if jump_type.should_jump(&self.registers.f) {
+            (self.read_next_word(), 4)
+        } else {
+            (self.pc.wrapping_add(3), 3)
+        }
+    }
+
+    /// Runs `JR`, returning the next `pc` and the cycles it cost.
+    fn jump_relative(&mut self, jump_type: &JumpType) -> (u16, u8) {
+        let next_pc = self.pc.wrapping_add(2);
+
+        if jump_type.should_jump(&self.registers.f) {
+            let offset = self.read_next_byte() as i8;
+            (next_pc.wrapping_add(offset as u16), 3)
         } else {
-            self.pc.wrapping_add(3)
+            (next_pc, 2)
         }
     }
 
@@ -219,21 +1058,231 @@ impl CPU {
         get_u16(most_significant_byte, least_significant_byte)
     }
 
-    fn call(&mut self, should_jump: bool) -> u16 {
+    /// Runs `CALL`, returning the next `pc` and the cycles it cost.
+    fn call(&mut self, jump_type: &JumpType) -> (u16, u8) {
         let next_pc = self.pc.wrapping_add(3);
-        if should_jump {
+        if jump_type.should_jump(&self.registers.f) {
             self.push(next_pc);
-            self.read_next_word()
+            (self.read_next_word(), 6)
         } else {
-            next_pc
+            (next_pc, 3)
         }
     }
 
-    fn return_(&mut self, should_jump: bool) -> u16 {
-        if should_jump {
-            self.pop()
+    /// Runs `RET`, returning the next `pc` and the cycles it cost. An
+    /// unconditional `RET` always costs 4; a conditional `RET` costs 5 when
+    /// taken and 2 when it falls through.
+    fn return_(&mut self, jump_type: &JumpType) -> (u16, u8) {
+        if jump_type.should_jump(&self.registers.f) {
+            let cycles = if matches!(jump_type, JumpType::Always) {
+                4
+            } else {
+                5
+            };
+            (self.pop(), cycles)
         } else {
-            self.pc.wrapping_add(1)
+            (self.pc.wrapping_add(1), 2)
+        }
+    }
+
+    /// Freezes the whole machine (registers, pc/sp, interrupt state, the
+    /// running cycle counter, and the bus) into a versioned binary blob and
+    /// writes it to the numbered save slot.
+    pub fn save_state(&self, slot: u8) -> std::io::Result<()> {
+        std::fs::write(save_state_path(slot), self.snapshot())
+    }
+
+    /// Restores the machine from the given numbered save slot.
+    pub fn load_state(&mut self, slot: u8) -> std::io::Result<()> {
+        let bytes = std::fs::read(save_state_path(slot))?;
+        self.restore(&bytes)
+    }
+
+    /// Restores whichever `.state` file in the current directory was
+    /// written most recently, rather than a specific slot, so a "quick
+    /// load" always resumes the player's latest snapshot.
+    pub fn load_latest_state(&mut self) -> std::io::Result<()> {
+        let path = save_states_by_mtime()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no save states found"))?;
+
+        let bytes = std::fs::read(path)?;
+        self.restore(&bytes)
+    }
+
+    /// Lists `.state` files in the current directory, newest first, for
+    /// presenting a "resume" menu ordered by when they were written rather
+    /// than by filename.
+    pub fn list_save_states() -> std::io::Result<Vec<std::path::PathBuf>> {
+        save_states_by_mtime()
+    }
+
+    /// Freezes the whole machine into the same versioned binary blob
+    /// [`CPU::save_state`] writes to disk, for callers that want the raw
+    /// snapshot bytes directly (e.g. to hand off over the network).
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend_from_slice(SAVE_STATE_MAGIC);
+        buf.push(SAVE_STATE_VERSION);
+
+        buf.push(self.registers.a);
+        buf.push(self.registers.b);
+        buf.push(self.registers.c);
+        buf.push(self.registers.d);
+        buf.push(self.registers.e);
+        buf.push(u8::from(self.registers.f));
+        buf.push(self.registers.h);
+        buf.push(self.registers.l);
+        buf.extend_from_slice(&self.pc.to_le_bytes());
+        buf.extend_from_slice(&self.sp.to_le_bytes());
+        buf.push(self.is_halted as u8);
+        buf.push(self.ime as u8);
+        buf.push(self.ime_enable_delay);
+        buf.extend_from_slice(&self.cycles.to_le_bytes());
+        buf.extend_from_slice(&self.bus.snapshot());
+
+        buf
+    }
+
+    /// Restores state previously produced by [`CPU::snapshot`].
+    pub fn restore(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        if bytes.len() < 5 || &bytes[0..4] != SAVE_STATE_MAGIC || bytes[4] != SAVE_STATE_VERSION {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "unrecognized save state",
+            ));
         }
+
+        let mut cursor = 5;
+
+        self.registers.a = bytes[cursor];
+        self.registers.b = bytes[cursor + 1];
+        self.registers.c = bytes[cursor + 2];
+        self.registers.d = bytes[cursor + 3];
+        self.registers.e = bytes[cursor + 4];
+        self.registers.f = FlagsRegister::from(bytes[cursor + 5]);
+        self.registers.h = bytes[cursor + 6];
+        self.registers.l = bytes[cursor + 7];
+        cursor += 8;
+
+        self.pc = u16::from_le_bytes([bytes[cursor], bytes[cursor + 1]]);
+        cursor += 2;
+        self.sp = u16::from_le_bytes([bytes[cursor], bytes[cursor + 1]]);
+        cursor += 2;
+
+        self.is_halted = bytes[cursor] != 0;
+        self.ime = bytes[cursor + 1] != 0;
+        self.ime_enable_delay = bytes[cursor + 2];
+        cursor += 3;
+
+        self.cycles = u64::from_le_bytes(bytes[cursor..cursor + 8].try_into().unwrap());
+        cursor += 8;
+
+        self.bus.restore(&bytes[cursor..])?;
+
+        Ok(())
+    }
+}
+
+const SAVE_STATE_MAGIC: &[u8; 4] = b"GBST";
+const SAVE_STATE_VERSION: u8 = 1;
+
+fn save_state_path(slot: u8) -> std::path::PathBuf {
+    std::path::PathBuf::from(format!("slot{}.state", slot))
+}
+
+/// Scans the current directory for `.state` files and returns them newest
+/// modification time first.
+fn save_states_by_mtime() -> std::io::Result<Vec<std::path::PathBuf>> {
+    let mut states = Vec::new();
+
+    for entry in std::fs::read_dir(".")? {
+        let entry = entry?;
+        let path = entry.path();
+
+        if path.extension().and_then(|ext| ext.to_str()) != Some("state") {
+            continue;
+        }
+
+        let modified = entry.metadata()?.modified()?;
+        states.push((modified, path));
+    }
+
+    states.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+    Ok(states.into_iter().map(|(_, path)| path).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `EI` only takes effect before the *second* instruction fetched after
+    /// it, so a pending interrupt must not fire on the very next step, only
+    /// on the one after that.
+    #[test]
+    fn ei_delays_interrupt_dispatch_by_one_instruction() {
+        let mut bus = MemoryBus::new(None);
+        bus.write_byte(0xC000, 0xFB); // EI
+        bus.write_byte(0xC001, 0x00); // NOP
+        bus.write_byte(0xC002, 0x00); // NOP
+        bus.interrupt_enable = 1 << VBLANK_INTERRUPT_BIT;
+        bus.interrupt_flag = 1 << VBLANK_INTERRUPT_BIT;
+
+        let mut cpu = CPU::new(bus);
+        cpu.pc = 0xC000;
+
+        cpu.step_cycles(); // runs EI
+        assert_eq!(cpu.pc(), 0xC001);
+
+        cpu.step_cycles(); // runs the NOP right after EI; interrupt still held off
+        assert_eq!(cpu.pc(), 0xC002);
+
+        cpu.step_cycles(); // IME is live now; the pending interrupt fires instead of the second NOP
+        assert_eq!(cpu.pc(), VBLANK_VECTOR);
+    }
+
+    /// `DAA` corrects a binary `ADD` into packed BCD: 0x45 + 0x38 is 0x7D in
+    /// binary, but 45 + 38 is 83 in decimal, so `DAA` must turn 0x7D into
+    /// 0x83.
+    #[test]
+    fn daa_corrects_a_binary_add_into_packed_bcd() {
+        let mut bus = MemoryBus::new(None);
+        bus.write_byte(0xC000, 0x3E); // LD A,0x45
+        bus.write_byte(0xC001, 0x45);
+        bus.write_byte(0xC002, 0xC6); // ADD A,0x38
+        bus.write_byte(0xC003, 0x38);
+        bus.write_byte(0xC004, 0x27); // DAA
+
+        let mut cpu = CPU::new(bus);
+        cpu.pc = 0xC000;
+
+        cpu.step_cycles();
+        cpu.step_cycles();
+        cpu.step_cycles();
+
+        assert_eq!(cpu.registers.a, 0x83);
+        assert!(!cpu.registers.f.carry);
+        assert!(!cpu.registers.f.zero);
+    }
+
+    #[test]
+    fn cpl_complements_a_and_sets_subtract_and_half_carry() {
+        let mut bus = MemoryBus::new(None);
+        bus.write_byte(0xC000, 0x3E); // LD A,0x3C
+        bus.write_byte(0xC001, 0x3C);
+        bus.write_byte(0xC002, 0x2F); // CPL
+
+        let mut cpu = CPU::new(bus);
+        cpu.pc = 0xC000;
+
+        cpu.step_cycles();
+        cpu.step_cycles();
+
+        assert_eq!(cpu.registers.a, 0xC3);
+        assert!(cpu.registers.f.subtract);
+        assert!(cpu.registers.f.half_carry);
     }
 }
@@ -1,16 +1,53 @@
 use super::FlagsRegister;
 
+include!(concat!(env!("OUT_DIR"), "/opcode_metadata.rs"));
+
+#[derive(Debug)]
 pub enum ArithmeticTarget {
     A,
     B,
     C,
     D,
     E,
-    F,
     H,
     L,
+    HLI,
+    D8,
+}
+
+#[derive(Debug)]
+pub enum IncDecTarget {
+    A,
+    B,
+    C,
+    D,
+    E,
+    H,
+    L,
+    HLI,
+}
+
+#[derive(Debug)]
+pub enum PrefixTarget {
+    A,
+    B,
+    C,
+    D,
+    E,
+    H,
+    L,
+    HLI,
+}
+
+#[derive(Debug)]
+pub enum WordRegister {
+    BC,
+    DE,
+    HL,
+    SP,
 }
 
+#[derive(Debug)]
 pub enum JumpType {
     NotZero,
     Zero,
@@ -33,6 +70,7 @@ impl JumpType {
     }
 }
 
+#[derive(Debug)]
 pub enum LoadByteTarget {
     A,
     B,
@@ -42,8 +80,11 @@ pub enum LoadByteTarget {
     H,
     L,
     HLI,
+    HLINC,
+    HLDEC,
 }
 
+#[derive(Debug)]
 pub enum LoadByteSource {
     A,
     B,
@@ -54,26 +95,40 @@ pub enum LoadByteSource {
     L,
     D8,
     HLI,
+    HLINC,
+    HLDEC,
 }
 
+#[derive(Debug)]
 pub enum LoadTargetFromA {
     BC,
     DE,
     D16,
+    C,
+    A8,
 }
 
+#[derive(Debug)]
 pub enum LoadAFromSource {
     BC,
     DE,
     D16,
+    C,
+    A8,
 }
 
+#[derive(Debug)]
 pub enum LoadType {
     Byte(LoadByteTarget, LoadByteSource),
+    Word(WordRegister),
     FromA(LoadTargetFromA),
     ToA(LoadAFromSource),
+    SPFromHL,
+    HLFromSPOffset,
+    IndirectFromSP,
 }
 
+#[derive(Debug)]
 pub enum PushSource {
     BC,
     DE,
@@ -81,6 +136,7 @@ pub enum PushSource {
     AF,
 }
 
+#[derive(Debug)]
 pub enum PopTarget {
     BC,
     DE,
@@ -88,16 +144,136 @@ pub enum PopTarget {
     AF,
 }
 
+#[derive(Debug)]
 pub enum Instruction {
     ADD(ArithmeticTarget),
+    ADC(ArithmeticTarget),
+    SUB(ArithmeticTarget),
+    SBC(ArithmeticTarget),
+    AND(ArithmeticTarget),
+    OR(ArithmeticTarget),
+    XOR(ArithmeticTarget),
+    CP(ArithmeticTarget),
+    INC(IncDecTarget),
+    DEC(IncDecTarget),
+    INC16(WordRegister),
+    DEC16(WordRegister),
+    ADDHL(WordRegister),
+    ADDSP,
+    RLCA,
+    RRCA,
+    RLA,
+    RRA,
+    RLC(PrefixTarget),
+    RRC(PrefixTarget),
+    RL(PrefixTarget),
+    RR(PrefixTarget),
+    SLA(PrefixTarget),
+    SRA(PrefixTarget),
+    SWAP(PrefixTarget),
+    SRL(PrefixTarget),
+    BIT(u8, PrefixTarget),
+    SET(u8, PrefixTarget),
+    RES(u8, PrefixTarget),
     JP(JumpType),
+    JPI,
+    JR(JumpType),
     LD(LoadType),
     PUSH(PushSource),
     POP(PopTarget),
     CALL(JumpType),
     RET(JumpType),
+    RETI,
+    RST(u8),
     NOP,
     HALT,
+    STOP,
+    DAA,
+    CPL,
+    SCF,
+    CCF,
+    EI,
+    DI,
+}
+
+fn arithmetic_target_from_column(column: u8) -> ArithmeticTarget {
+    match column & 0x07 {
+        0 => ArithmeticTarget::B,
+        1 => ArithmeticTarget::C,
+        2 => ArithmeticTarget::D,
+        3 => ArithmeticTarget::E,
+        4 => ArithmeticTarget::H,
+        5 => ArithmeticTarget::L,
+        6 => ArithmeticTarget::HLI,
+        7 => ArithmeticTarget::A,
+        _ => unreachable!(),
+    }
+}
+
+fn inc_dec_target_from_row(row: u8) -> IncDecTarget {
+    match row {
+        0 => IncDecTarget::B,
+        1 => IncDecTarget::C,
+        2 => IncDecTarget::D,
+        3 => IncDecTarget::E,
+        4 => IncDecTarget::H,
+        5 => IncDecTarget::L,
+        6 => IncDecTarget::HLI,
+        7 => IncDecTarget::A,
+        _ => unreachable!(),
+    }
+}
+
+fn load_byte_source_from_column(column: u8) -> LoadByteSource {
+    match column & 0x07 {
+        0 => LoadByteSource::B,
+        1 => LoadByteSource::C,
+        2 => LoadByteSource::D,
+        3 => LoadByteSource::E,
+        4 => LoadByteSource::H,
+        5 => LoadByteSource::L,
+        6 => LoadByteSource::HLI,
+        7 => LoadByteSource::A,
+        _ => unreachable!(),
+    }
+}
+
+fn load_byte_target_from_row(row: u8) -> LoadByteTarget {
+    match row {
+        0 => LoadByteTarget::B,
+        1 => LoadByteTarget::C,
+        2 => LoadByteTarget::D,
+        3 => LoadByteTarget::E,
+        4 => LoadByteTarget::H,
+        5 => LoadByteTarget::L,
+        6 => LoadByteTarget::HLI,
+        7 => LoadByteTarget::A,
+        _ => unreachable!(),
+    }
+}
+
+fn word_register_from_pair(pair: u8) -> WordRegister {
+    match pair & 0x03 {
+        0 => WordRegister::BC,
+        1 => WordRegister::DE,
+        2 => WordRegister::HL,
+        3 => WordRegister::SP,
+        _ => unreachable!(),
+    }
+}
+
+fn prefix_target_from_column(column: u8) -> PrefixTarget {
+    match column & 0x07 {
+        0 => PrefixTarget::B,
+        1 => PrefixTarget::C,
+        2 => PrefixTarget::D,
+        3 => PrefixTarget::E,
+        4 => PrefixTarget::H,
+        5 => PrefixTarget::L,
+        6 => PrefixTarget::HLI,
+        7 => PrefixTarget::A,
+        _ => unreachable!(),
+    }
 }
 
 impl Instruction {
@@ -109,17 +285,217 @@ impl Instruction {
         }
     }
 
+    /// Looks up `opcode`'s `(mnemonic, length, cycles_taken,
+    /// cycles_untaken)`, generated from `instructions.in` by `build.rs`.
+    pub fn opcode_meta(opcode: u8, prefixed: bool) -> OpcodeMeta {
+        if prefixed {
+            OPCODE_META_PREFIXED[opcode as usize]
+        } else {
+            OPCODE_META[opcode as usize]
+        }
+    }
+
     pub fn from_opcode_prefixed(opcode: u8) -> Option<Instruction> {
+        let column = opcode & 0x07;
+        let target = prefix_target_from_column(column);
+
         match opcode {
-            0x02 => None,
-            _ => None,
+            0x00..=0x07 => Some(Instruction::RLC(target)),
+            0x08..=0x0F => Some(Instruction::RRC(target)),
+            0x10..=0x17 => Some(Instruction::RL(target)),
+            0x18..=0x1F => Some(Instruction::RR(target)),
+            0x20..=0x27 => Some(Instruction::SLA(target)),
+            0x28..=0x2F => Some(Instruction::SRA(target)),
+            0x30..=0x37 => Some(Instruction::SWAP(target)),
+            0x38..=0x3F => Some(Instruction::SRL(target)),
+            0x40..=0x7F => {
+                let bit = (opcode - 0x40) >> 3;
+                Some(Instruction::BIT(bit, target))
+            }
+            0x80..=0xBF => {
+                let bit = (opcode - 0x80) >> 3;
+                Some(Instruction::RES(bit, target))
+            }
+            0xC0..=0xFF => {
+                let bit = (opcode - 0xC0) >> 3;
+                Some(Instruction::SET(bit, target))
+            }
         }
     }
 
     pub fn from_opcode_not_prefixed(opcode: u8) -> Option<Instruction> {
         match opcode {
-            0x02 => None,
-            _ => None,
+            0x00 => Some(Instruction::NOP),
+            0x01 | 0x11 | 0x21 | 0x31 => {
+                Some(Instruction::LD(LoadType::Word(word_register_from_pair(opcode >> 4))))
+            }
+            0x02 => Some(Instruction::LD(LoadType::FromA(LoadTargetFromA::BC))),
+            0x12 => Some(Instruction::LD(LoadType::FromA(LoadTargetFromA::DE))),
+            0x0A => Some(Instruction::LD(LoadType::ToA(LoadAFromSource::BC))),
+            0x1A => Some(Instruction::LD(LoadType::ToA(LoadAFromSource::DE))),
+            0x03 | 0x13 | 0x23 | 0x33 => {
+                Some(Instruction::INC16(word_register_from_pair(opcode >> 4)))
+            }
+            0x0B | 0x1B | 0x2B | 0x3B => {
+                Some(Instruction::DEC16(word_register_from_pair(opcode >> 4)))
+            }
+            0x09 | 0x19 | 0x29 | 0x39 => {
+                Some(Instruction::ADDHL(word_register_from_pair(opcode >> 4)))
+            }
+            0x04 | 0x0C | 0x14 | 0x1C | 0x24 | 0x2C | 0x34 | 0x3C => {
+                let row = (opcode - 0x04) >> 3;
+                Some(Instruction::INC(inc_dec_target_from_row(row)))
+            }
+            0x05 | 0x0D | 0x15 | 0x1D | 0x25 | 0x2D | 0x35 | 0x3D => {
+                let row = (opcode - 0x05) >> 3;
+                Some(Instruction::DEC(inc_dec_target_from_row(row)))
+            }
+            0x06 | 0x0E | 0x16 | 0x1E | 0x26 | 0x2E | 0x36 | 0x3E => {
+                let row = (opcode - 0x06) >> 3;
+                Some(Instruction::LD(LoadType::Byte(
+                    load_byte_target_from_row(row),
+                    LoadByteSource::D8,
+                )))
+            }
+            0x07 => Some(Instruction::RLCA),
+            0x0F => Some(Instruction::RRCA),
+            0x17 => Some(Instruction::RLA),
+            0x1F => Some(Instruction::RRA),
+            0x08 => Some(Instruction::LD(LoadType::IndirectFromSP)),
+            0x10 => Some(Instruction::STOP),
+            0x18 => Some(Instruction::JR(JumpType::Always)),
+            0x20 => Some(Instruction::JR(JumpType::NotZero)),
+            0x28 => Some(Instruction::JR(JumpType::Zero)),
+            0x30 => Some(Instruction::JR(JumpType::NotCarry)),
+            0x38 => Some(Instruction::JR(JumpType::Carry)),
+            0x22 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::HLINC,
+                LoadByteSource::A,
+            ))),
+            0x2A => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::A,
+                LoadByteSource::HLINC,
+            ))),
+            0x32 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::HLDEC,
+                LoadByteSource::A,
+            ))),
+            0x3A => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::A,
+                LoadByteSource::HLDEC,
+            ))),
+            0x27 => Some(Instruction::DAA),
+            0x2F => Some(Instruction::CPL),
+            0x37 => Some(Instruction::SCF),
+            0x3F => Some(Instruction::CCF),
+            0x76 => Some(Instruction::HALT),
+            0x40..=0x7F => {
+                let row = (opcode - 0x40) >> 3;
+                let column = opcode & 0x07;
+                Some(Instruction::LD(LoadType::Byte(
+                    load_byte_target_from_row(row),
+                    load_byte_source_from_column(column),
+                )))
+            }
+            0x80..=0x87 => Some(Instruction::ADD(arithmetic_target_from_column(opcode))),
+            0x88..=0x8F => Some(Instruction::ADC(arithmetic_target_from_column(opcode))),
+            0x90..=0x97 => Some(Instruction::SUB(arithmetic_target_from_column(opcode))),
+            0x98..=0x9F => Some(Instruction::SBC(arithmetic_target_from_column(opcode))),
+            0xA0..=0xA7 => Some(Instruction::AND(arithmetic_target_from_column(opcode))),
+            0xA8..=0xAF => Some(Instruction::XOR(arithmetic_target_from_column(opcode))),
+            0xB0..=0xB7 => Some(Instruction::OR(arithmetic_target_from_column(opcode))),
+            0xB8..=0xBF => Some(Instruction::CP(arithmetic_target_from_column(opcode))),
+            0xC6 => Some(Instruction::ADD(ArithmeticTarget::D8)),
+            0xCE => Some(Instruction::ADC(ArithmeticTarget::D8)),
+            0xD6 => Some(Instruction::SUB(ArithmeticTarget::D8)),
+            0xDE => Some(Instruction::SBC(ArithmeticTarget::D8)),
+            0xE6 => Some(Instruction::AND(ArithmeticTarget::D8)),
+            0xEE => Some(Instruction::XOR(ArithmeticTarget::D8)),
+            0xF6 => Some(Instruction::OR(ArithmeticTarget::D8)),
+            0xFE => Some(Instruction::CP(ArithmeticTarget::D8)),
+            0xC0 => Some(Instruction::RET(JumpType::NotZero)),
+            0xC8 => Some(Instruction::RET(JumpType::Zero)),
+            0xD0 => Some(Instruction::RET(JumpType::NotCarry)),
+            0xD8 => Some(Instruction::RET(JumpType::Carry)),
+            0xC9 => Some(Instruction::RET(JumpType::Always)),
+            0xD9 => Some(Instruction::RETI),
+            0xC2 => Some(Instruction::JP(JumpType::NotZero)),
+            0xCA => Some(Instruction::JP(JumpType::Zero)),
+            0xD2 => Some(Instruction::JP(JumpType::NotCarry)),
+            0xDA => Some(Instruction::JP(JumpType::Carry)),
+            0xC3 => Some(Instruction::JP(JumpType::Always)),
+            0xE9 => Some(Instruction::JPI),
+            0xC4 => Some(Instruction::CALL(JumpType::NotZero)),
+            0xCC => Some(Instruction::CALL(JumpType::Zero)),
+            0xD4 => Some(Instruction::CALL(JumpType::NotCarry)),
+            0xDC => Some(Instruction::CALL(JumpType::Carry)),
+            0xCD => Some(Instruction::CALL(JumpType::Always)),
+            0xC1 => Some(Instruction::POP(PopTarget::BC)),
+            0xD1 => Some(Instruction::POP(PopTarget::DE)),
+            0xE1 => Some(Instruction::POP(PopTarget::HL)),
+            0xF1 => Some(Instruction::POP(PopTarget::AF)),
+            0xC5 => Some(Instruction::PUSH(PushSource::BC)),
+            0xD5 => Some(Instruction::PUSH(PushSource::DE)),
+            0xE5 => Some(Instruction::PUSH(PushSource::HL)),
+            0xF5 => Some(Instruction::PUSH(PushSource::AF)),
+            0xC7 => Some(Instruction::RST(0x00)),
+            0xCF => Some(Instruction::RST(0x08)),
+            0xD7 => Some(Instruction::RST(0x10)),
+            0xDF => Some(Instruction::RST(0x18)),
+            0xE7 => Some(Instruction::RST(0x20)),
+            0xEF => Some(Instruction::RST(0x28)),
+            0xF7 => Some(Instruction::RST(0x30)),
+            0xFF => Some(Instruction::RST(0x38)),
+            0xE0 => Some(Instruction::LD(LoadType::FromA(LoadTargetFromA::A8))),
+            0xF0 => Some(Instruction::LD(LoadType::ToA(LoadAFromSource::A8))),
+            0xE2 => Some(Instruction::LD(LoadType::FromA(LoadTargetFromA::C))),
+            0xF2 => Some(Instruction::LD(LoadType::ToA(LoadAFromSource::C))),
+            0xEA => Some(Instruction::LD(LoadType::FromA(LoadTargetFromA::D16))),
+            0xFA => Some(Instruction::LD(LoadType::ToA(LoadAFromSource::D16))),
+            0xE8 => Some(Instruction::ADDSP),
+            0xF8 => Some(Instruction::LD(LoadType::HLFromSPOffset)),
+            0xF9 => Some(Instruction::LD(LoadType::SPFromHL)),
+            0xF3 => Some(Instruction::DI),
+            0xFB => Some(Instruction::EI),
+            0xCB => None,
+            0xD3 | 0xDB | 0xDD | 0xE3 | 0xE4 | 0xEB | 0xEC | 0xED | 0xF4 | 0xFC | 0xFD => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The LR35902 has 11 undefined opcodes in its unprefixed table
+    /// (`0xD3`, `0xDB`, `0xDD`, `0xE3`, `0xE4`, `0xEB`, `0xEC`, `0xED`,
+    /// `0xF4`, `0xFC`, `0xFD`) plus `0xCB`, which is the prefix byte rather
+    /// than an instruction of its own. Every other byte should decode.
+    const UNDEFINED_OPCODES: [u8; 12] = [
+        0xCB, 0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD,
+    ];
+
+    #[test]
+    fn unprefixed_table_covers_every_defined_opcode() {
+        for opcode in 0..=u8::MAX {
+            let decoded = Instruction::from_opcode_not_prefixed(opcode);
+
+            if UNDEFINED_OPCODES.contains(&opcode) {
+                assert!(decoded.is_none(), "0x{:02X} should be undefined", opcode);
+            } else {
+                assert!(decoded.is_some(), "0x{:02X} should decode", opcode);
+            }
+        }
+    }
+
+    #[test]
+    fn prefixed_table_covers_every_opcode() {
+        for opcode in 0..=u8::MAX {
+            assert!(
+                Instruction::from_opcode_prefixed(opcode).is_some(),
+                "CB 0x{:02X} should decode",
+                opcode
+            );
         }
     }
 }
@@ -0,0 +1,188 @@
+use std::collections::HashSet;
+
+use crate::{Instruction, CPU};
+
+/// A CLI monitor wrapped around a [`CPU`]: set breakpoints, single-step,
+/// dump registers/memory, and disassemble the instruction at `pc` the way
+/// a text-based debugger would.
+pub struct Debugger<'a> {
+    cpu: &'a mut CPU,
+    breakpoints: HashSet<u16>,
+    last_command: Option<String>,
+    trace: bool,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(cpu: &'a mut CPU) -> Debugger<'a> {
+        Debugger {
+            cpu,
+            breakpoints: HashSet::new(),
+            last_command: None,
+            trace: false,
+        }
+    }
+
+    pub fn set_trace(&mut self, enabled: bool) {
+        self.trace = enabled;
+    }
+
+    pub fn add_breakpoint(&mut self, address: u16) {
+        self.breakpoints.insert(address);
+    }
+
+    pub fn remove_breakpoint(&mut self, address: u16) {
+        self.breakpoints.remove(&address);
+    }
+
+    pub fn at_breakpoint(&self) -> bool {
+        self.breakpoints.contains(&self.cpu.pc())
+    }
+
+    /// Single-steps `count` instructions, printing a trace line per
+    /// instruction when trace mode is enabled.
+    pub fn step(&mut self, count: usize) {
+        for _ in 0..count {
+            if self.trace {
+                println!("{}", self.disassemble_at_pc());
+            }
+
+            self.cpu.step_cycles();
+        }
+    }
+
+    /// Steps until `pc` lands on a breakpoint, or `STEP_LIMIT` instructions
+    /// have run without hitting one (a runaway guard, since an unset
+    /// breakpoint would otherwise spin forever). Returns whether a
+    /// breakpoint was actually hit.
+    pub fn run_until_breakpoint(&mut self) -> bool {
+        const STEP_LIMIT: usize = 10_000_000;
+
+        for _ in 0..STEP_LIMIT {
+            self.step(1);
+
+            if self.at_breakpoint() {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Formats `{pc}: <raw bytes> <mnemonic>`, the mnemonic and instruction
+    /// length coming from the generated opcode metadata table rather than
+    /// `Instruction`'s `Debug` form.
+    pub fn disassemble_at_pc(&self) -> String {
+        let pc = self.cpu.pc();
+        let first_byte = self.cpu.bus().read_byte(pc);
+        let prefixed = first_byte == 0xCB;
+        let opcode = if prefixed {
+            self.cpu.bus().read_byte(pc.wrapping_add(1))
+        } else {
+            first_byte
+        };
+
+        let (mnemonic, length, _, _) = Instruction::opcode_meta(opcode, prefixed);
+
+        let raw_bytes: Vec<String> = (0..length as u16)
+            .map(|offset| format!("{:02x}", self.cpu.bus().read_byte(pc.wrapping_add(offset))))
+            .collect();
+
+        format!("{:#06x}: {:<8} {}", pc, raw_bytes.join(" "), mnemonic)
+    }
+
+    pub fn dump_registers(&self) -> String {
+        let registers = self.cpu.registers();
+        let flags = registers.f;
+
+        format!(
+            "a={:02x} b={:02x} c={:02x} d={:02x} e={:02x} h={:02x} l={:02x}\n\
+             pc={:04x} sp={:04x}\n\
+             flags: z={} n={} h={} c={}",
+            registers.a,
+            registers.b,
+            registers.c,
+            registers.d,
+            registers.e,
+            registers.h,
+            registers.l,
+            self.cpu.pc(),
+            self.cpu.sp(),
+            flags.zero as u8,
+            flags.subtract as u8,
+            flags.half_carry as u8,
+            flags.carry as u8,
+        )
+    }
+
+    pub fn dump_memory(&self, address: u16, length: u16) -> String {
+        let mut lines = Vec::new();
+
+        for row_start in (0..length).step_by(16) {
+            let row_address = address.wrapping_add(row_start);
+            let bytes: Vec<String> = (0..16.min(length - row_start))
+                .map(|offset| format!("{:02x}", self.cpu.bus().read_byte(row_address.wrapping_add(offset))))
+                .collect();
+
+            lines.push(format!("{:#06x}: {}", row_address, bytes.join(" ")));
+        }
+
+        lines.join("\n")
+    }
+
+    /// Parses and runs a single debugger command, returning the text to
+    /// print. Supports `b <addr>` (set a breakpoint), `s [n]` (single-step
+    /// `n` instructions, 1 when given no argument), `c` (run until a
+    /// breakpoint is hit), `r` (dump registers), `m <addr> [len]` (dump
+    /// memory), and `d` (disassemble the instruction at `pc`). An empty
+    /// line repeats the last command.
+    pub fn execute_command(&mut self, input: &str) -> String {
+        let input = input.trim();
+        let command = if input.is_empty() {
+            self.last_command.clone().unwrap_or_default()
+        } else {
+            input.to_string()
+        };
+
+        self.last_command = Some(command.clone());
+
+        let mut parts = command.split_whitespace();
+        let verb = parts.next().unwrap_or("");
+
+        match verb {
+            "b" => match parts.next().and_then(parse_address) {
+                Some(address) => {
+                    self.add_breakpoint(address);
+                    format!("breakpoint set at {:#06x}", address)
+                }
+                None => "usage: b <addr>".to_string(),
+            },
+            "s" => {
+                let count = parts.next().and_then(|arg| arg.parse().ok()).unwrap_or(1);
+                self.step(count);
+                format!("stepped {} instruction(s)", count)
+            }
+            "c" => {
+                if self.run_until_breakpoint() {
+                    format!("hit breakpoint at {:#06x}", self.cpu.pc())
+                } else {
+                    format!("stopped at {:#06x} (no breakpoint hit)", self.cpu.pc())
+                }
+            }
+            "r" => self.dump_registers(),
+            "m" => match parts.next().and_then(parse_address) {
+                Some(address) => {
+                    let length = parts.next().and_then(|arg| arg.parse().ok()).unwrap_or(16);
+                    self.dump_memory(address, length)
+                }
+                None => "usage: m <addr> [len]".to_string(),
+            },
+            "d" => self.disassemble_at_pc(),
+            _ => format!("unknown command: {}", command),
+        }
+    }
+}
+
+fn parse_address(arg: &str) -> Option<u16> {
+    let trimmed = arg.trim_start_matches("0x");
+    u16::from_str_radix(trimmed, 16).ok()
+}
@@ -2,23 +2,116 @@ pub const VRAM_BEGIN: usize = 0x8000;
 pub const VRAM_END: usize = 0x9FFF;
 pub const VRAM_SIZE: usize = VRAM_END - VRAM_BEGIN + 1;
 
-#[derive(Copy, Clone)]
-enum TilePixelValue {
-    White,
-    Gray,
-    Light,
-    Black,
-}
+pub const OAM_BEGIN: usize = 0xFE00;
+pub const OAM_END: usize = 0xFE9F;
+pub const OAM_SIZE: usize = OAM_END - OAM_BEGIN + 1;
+
+pub const LCDC_ADDRESS: u16 = 0xFF40;
+pub const STAT_ADDRESS: u16 = 0xFF41;
+pub const SCY_ADDRESS: u16 = 0xFF42;
+pub const SCX_ADDRESS: u16 = 0xFF43;
+pub const LY_ADDRESS: u16 = 0xFF44;
+pub const LYC_ADDRESS: u16 = 0xFF45;
+pub const BGP_ADDRESS: u16 = 0xFF47;
+pub const OBP0_ADDRESS: u16 = 0xFF48;
+pub const OBP1_ADDRESS: u16 = 0xFF49;
+pub const WY_ADDRESS: u16 = 0xFF4A;
+pub const WX_ADDRESS: u16 = 0xFF4B;
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
 
-type Tile = [[TilePixelValue; 8]; 8];
+const OAM_SEARCH_DOTS: u32 = 80;
+const PIXEL_TRANSFER_DOTS: u32 = 172;
+const HBLANK_DOTS: u32 = 204;
+const SCANLINE_DOTS: u32 = OAM_SEARCH_DOTS + PIXEL_TRANSFER_DOTS + HBLANK_DOTS;
+
+/// Maps a two-bit BGP/OBP0/OBP1 shade index to an RGBA color, lightest first.
+const SHADE_COLORS: [[u8; 4]; 4] = [
+    [0xFF, 0xFF, 0xFF, 0xFF],
+    [0xAA, 0xAA, 0xAA, 0xFF],
+    [0x55, 0x55, 0x55, 0xFF],
+    [0x00, 0x00, 0x00, 0xFF],
+];
+
+/// An 8x8 tile of raw two-bit pixel values decoded from tile data. Each 0-3 index is only
+/// turned into an actual shade at render time, via whichever of
+/// BGP/OBP0/OBP1 applies.
+type Tile = [[u8; 8]; 8];
 
 pub fn empty_tile() -> Tile {
-    [[TilePixelValue::Gray; 8]; 8]
+    [[0; 8]; 8]
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    OamSearch,
+    PixelTransfer,
+    HBlank,
+    VBlank,
+}
+
+impl Mode {
+    fn bits(self) -> u8 {
+        match self {
+            Mode::HBlank => 0,
+            Mode::VBlank => 1,
+            Mode::OamSearch => 2,
+            Mode::PixelTransfer => 3,
+        }
+    }
+}
+
+/// The result of advancing the PPU by some cycles: which interrupt `IF`
+/// bits, if any, it wants raised.
+#[derive(Default)]
+pub struct PpuInterrupts {
+    pub vblank: bool,
+    pub lcd_stat: bool,
 }
 
 pub struct GPU {
     memory: [u8; VRAM_SIZE],
     tiles: [Tile; 384],
+    oam: [u8; OAM_SIZE],
+    pub lcdc: u8,
+    pub stat: u8,
+    pub scy: u8,
+    pub scx: u8,
+    pub ly: u8,
+    pub lyc: u8,
+    pub bgp: u8,
+    pub obp0: u8,
+    pub obp1: u8,
+    pub wy: u8,
+    pub wx: u8,
+    mode: Mode,
+    dot: u32,
+    frame_buffer: [u8; SCREEN_WIDTH * SCREEN_HEIGHT * 4],
+}
+
+impl Default for GPU {
+    fn default() -> Self {
+        GPU {
+            memory: [0; VRAM_SIZE],
+            tiles: [empty_tile(); 384],
+            oam: [0; OAM_SIZE],
+            lcdc: 0,
+            stat: 0,
+            scy: 0,
+            scx: 0,
+            ly: 0,
+            lyc: 0,
+            bgp: 0,
+            obp0: 0,
+            obp1: 0,
+            wy: 0,
+            wx: 0,
+            mode: Mode::OamSearch,
+            dot: 0,
+            frame_buffer: [0; SCREEN_WIDTH * SCREEN_HEIGHT * 4],
+        }
+    }
 }
 
 impl GPU {
@@ -35,25 +128,279 @@ impl GPU {
 
         let normalized_address = address & 0xFFFE;
 
-        let ms_byte = self.memory[normalized_address];
-        let ls_byte = self.memory[normalized_address + 1];
+        let low_plane = self.memory[normalized_address];
+        let high_plane = self.memory[normalized_address + 1];
 
         let tile_address = address / 16;
         let row_address = (address % 16) / 2;
 
         for pixel_address in 0..8 {
             let mask = 1 << (7 - pixel_address);
-            let l_ms_byte = ms_byte & mask;
-            let l_ls_byte = ls_byte & mask;
-
-            let value = match (l_ms_byte != 0, l_ls_byte != 0) {
-                (true, true) => TilePixelValue::White,
-                (true, false) => TilePixelValue::Gray,
-                (false, true) => TilePixelValue::Light,
-                (false, false) => TilePixelValue::Black,
+            let low_bit = (low_plane & mask != 0) as u8;
+            let high_bit = (high_plane & mask != 0) as u8;
+
+            self.tiles[tile_address][row_address][pixel_address] = (high_bit << 1) | low_bit;
+        }
+    }
+
+    pub fn read_oam(&self, address: usize) -> u8 {
+        self.oam[address]
+    }
+
+    pub fn write_oam(&mut self, address: usize, value: u8) {
+        self.oam[address] = value;
+    }
+
+    /// Raw VRAM bytes, for serializing into a save state.
+    pub fn vram_snapshot(&self) -> [u8; VRAM_SIZE] {
+        self.memory
+    }
+
+    /// Restores VRAM from a save state by replaying every byte through
+    /// `write_memory`, which rebuilds the decoded `tiles` cache as a side
+    /// effect.
+    pub fn restore_vram(&mut self, vram: &[u8]) {
+        for (address, &value) in vram.iter().enumerate() {
+            self.write_memory(address, value);
+        }
+    }
+
+    /// Raw OAM bytes, for serializing into a save state.
+    pub fn oam_snapshot(&self) -> [u8; OAM_SIZE] {
+        self.oam
+    }
+
+    /// Restores OAM from a save state produced by [`GPU::oam_snapshot`].
+    pub fn restore_oam(&mut self, oam: &[u8]) {
+        self.oam.copy_from_slice(oam);
+    }
+
+    /// The LCDC/STAT/SCY/SCX/LY/LYC/BGP/OBP0/OBP1/WY/WX registers, for
+    /// serializing into a save state. Order matches [`GPU::restore_registers`].
+    pub fn registers_snapshot(&self) -> [u8; 11] {
+        [
+            self.lcdc, self.stat, self.scy, self.scx, self.ly, self.lyc, self.bgp, self.obp0,
+            self.obp1, self.wy, self.wx,
+        ]
+    }
+
+    /// Restores the registers serialized by [`GPU::registers_snapshot`].
+    pub fn restore_registers(&mut self, registers: &[u8; 11]) {
+        self.lcdc = registers[0];
+        self.stat = registers[1];
+        self.scy = registers[2];
+        self.scx = registers[3];
+        self.ly = registers[4];
+        self.lyc = registers[5];
+        self.bgp = registers[6];
+        self.obp0 = registers[7];
+        self.obp1 = registers[8];
+        self.wy = registers[9];
+        self.wx = registers[10];
+    }
+
+    /// The composited 160x144 RGBA framebuffer, updated one scanline at a
+    /// time as `step` crosses into HBlank.
+    pub fn frame_buffer(&self) -> &[u8] {
+        &self.frame_buffer
+    }
+
+    /// Advances the OAM search -> pixel transfer -> HBlank -> VBlank mode
+    /// state machine by `t_cycles`, compositing a scanline into the
+    /// framebuffer on every HBlank entry and reporting which interrupt `IF`
+    /// bits should be raised.
+    pub fn step(&mut self, t_cycles: u32) -> PpuInterrupts {
+        let mut interrupts = PpuInterrupts::default();
+
+        if self.lcdc & 0x80 == 0 {
+            return interrupts;
+        }
+
+        self.dot += t_cycles;
+
+        match self.mode {
+            Mode::OamSearch => {
+                if self.dot >= OAM_SEARCH_DOTS {
+                    self.dot -= OAM_SEARCH_DOTS;
+                    self.set_mode(Mode::PixelTransfer, &mut interrupts);
+                }
+            }
+            Mode::PixelTransfer => {
+                if self.dot >= PIXEL_TRANSFER_DOTS {
+                    self.dot -= PIXEL_TRANSFER_DOTS;
+                    self.render_scanline();
+                    self.set_mode(Mode::HBlank, &mut interrupts);
+                }
+            }
+            Mode::HBlank => {
+                if self.dot >= HBLANK_DOTS {
+                    self.dot -= HBLANK_DOTS;
+                    self.advance_line(&mut interrupts);
+
+                    if self.ly == SCREEN_HEIGHT as u8 {
+                        self.set_mode(Mode::VBlank, &mut interrupts);
+                        interrupts.vblank = true;
+                    } else {
+                        self.set_mode(Mode::OamSearch, &mut interrupts);
+                    }
+                }
+            }
+            Mode::VBlank => {
+                if self.dot >= SCANLINE_DOTS {
+                    self.dot -= SCANLINE_DOTS;
+                    self.advance_line(&mut interrupts);
+
+                    if self.ly == 0 {
+                        self.set_mode(Mode::OamSearch, &mut interrupts);
+                    }
+                }
+            }
+        }
+
+        interrupts
+    }
+
+    fn set_mode(&mut self, mode: Mode, interrupts: &mut PpuInterrupts) {
+        self.mode = mode;
+        self.stat = (self.stat & !0x03) | mode.bits();
+
+        let stat_enabled = match mode {
+            Mode::HBlank => self.stat & 0x08 != 0,
+            Mode::VBlank => self.stat & 0x10 != 0,
+            Mode::OamSearch => self.stat & 0x20 != 0,
+            Mode::PixelTransfer => false,
+        };
+
+        if stat_enabled {
+            interrupts.lcd_stat = true;
+        }
+    }
+
+    fn advance_line(&mut self, interrupts: &mut PpuInterrupts) {
+        self.ly = if self.ly + 1 > 153 { 0 } else { self.ly + 1 };
+
+        let coincidence = self.ly == self.lyc;
+        self.stat = if coincidence {
+            self.stat | 0x04
+        } else {
+            self.stat & !0x04
+        };
+
+        if coincidence && self.stat & 0x40 != 0 {
+            interrupts.lcd_stat = true;
+        }
+    }
+
+    fn render_scanline(&mut self) {
+        let line = self.ly as usize;
+        if line >= SCREEN_HEIGHT {
+            return;
+        }
+
+        let bg_and_window_enabled = self.lcdc & 0x01 != 0;
+        let window_enabled = bg_and_window_enabled && self.lcdc & 0x20 != 0;
+        let sprites_enabled = self.lcdc & 0x02 != 0;
+        let tall_sprites = self.lcdc & 0x04 != 0;
+        let bg_tile_map = if self.lcdc & 0x08 != 0 { 0x1C00 } else { 0x1800 };
+        let window_tile_map = if self.lcdc & 0x40 != 0 { 0x1C00 } else { 0x1800 };
+        let signed_tile_addressing = self.lcdc & 0x10 == 0;
+
+        let mut bg_color_ids = [0u8; SCREEN_WIDTH];
+
+        for (x, bg_color_id) in bg_color_ids.iter_mut().enumerate() {
+            let in_window = window_enabled
+                && line >= self.wy as usize
+                && x + 7 >= self.wx as usize;
+
+            let (tile_map, tile_x, tile_y, pixel_x, pixel_y) = if in_window {
+                let window_x = x + 7 - self.wx as usize;
+                let window_y = line - self.wy as usize;
+                (window_tile_map, window_x / 8, window_y / 8, window_x % 8, window_y % 8)
+            } else {
+                let bg_x = (x + self.scx as usize) & 0xFF;
+                let bg_y = (line + self.scy as usize) & 0xFF;
+                (bg_tile_map, bg_x / 8, bg_y / 8, bg_x % 8, bg_y % 8)
+            };
+
+            let tile_number = self.memory[tile_map + tile_y * 32 + tile_x];
+            let tile_index = if signed_tile_addressing {
+                (256 + tile_number as i8 as i32) as usize
+            } else {
+                tile_number as usize
             };
 
-            self.tiles[tile_address][row_address][pixel_address] = value;
+            let color_id = if bg_and_window_enabled {
+                self.tiles[tile_index][pixel_y][pixel_x]
+            } else {
+                0
+            };
+
+            *bg_color_id = color_id;
+            self.plot(x, line, palette_shade(self.bgp, color_id));
+        }
+
+        if !sprites_enabled {
+            return;
+        }
+
+        let sprite_height: i32 = if tall_sprites { 16 } else { 8 };
+
+        for sprite in 0..40 {
+            let base = sprite * 4;
+            let sprite_y = self.oam[base] as i32 - 16;
+            let sprite_x = self.oam[base + 1] as i32 - 8;
+            let tile_number = self.oam[base + 2];
+            let attributes = self.oam[base + 3];
+
+            if (line as i32) < sprite_y || (line as i32) >= sprite_y + sprite_height {
+                continue;
+            }
+
+            let flip_x = attributes & 0x20 != 0;
+            let flip_y = attributes & 0x40 != 0;
+            let behind_background = attributes & 0x80 != 0;
+            let palette = if attributes & 0x10 != 0 { self.obp1 } else { self.obp0 };
+
+            let mut row = (line as i32 - sprite_y) as usize;
+            if flip_y {
+                row = sprite_height as usize - 1 - row;
+            }
+
+            let tile_index = if tall_sprites {
+                (tile_number & 0xFE) as usize + row / 8
+            } else {
+                tile_number as usize
+            };
+            let tile_row = row % 8;
+
+            for column in 0..8 {
+                let pixel_x = sprite_x + column as i32;
+                if pixel_x < 0 || pixel_x >= SCREEN_WIDTH as i32 {
+                    continue;
+                }
+
+                let sample_column = if flip_x { 7 - column } else { column };
+                let color_id = self.tiles[tile_index][tile_row][sample_column];
+
+                if color_id == 0 {
+                    continue;
+                }
+
+                if behind_background && bg_color_ids[pixel_x as usize] != 0 {
+                    continue;
+                }
+
+                self.plot(pixel_x as usize, line, palette_shade(palette, color_id));
+            }
         }
     }
+
+    fn plot(&mut self, x: usize, y: usize, shade: u8) {
+        let offset = (y * SCREEN_WIDTH + x) * 4;
+        self.frame_buffer[offset..offset + 4].copy_from_slice(&SHADE_COLORS[shade as usize]);
+    }
+}
+
+fn palette_shade(palette: u8, color_id: u8) -> u8 {
+    (palette >> (color_id * 2)) & 0x03
 }
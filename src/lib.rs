@@ -1,9 +1,15 @@
+mod apu;
+mod cartridge;
 mod cpu;
+mod debugger;
 mod gpu;
 mod memory;
 mod utils;
 
+pub use apu::*;
+pub use cartridge::*;
 pub use cpu::*;
+pub use debugger::*;
 pub use gpu::*;
 pub use memory::*;
 pub use utils::*;
@@ -1,26 +1,369 @@
-use crate::{GPU, VRAM_BEGIN, VRAM_END};
+use crate::{
+    Cartridge, APU, APU_REGISTERS_BEGIN, APU_REGISTERS_END, BGP_ADDRESS, GPU, LCDC_ADDRESS,
+    LYC_ADDRESS, LY_ADDRESS, OAM_BEGIN, OAM_END, OBP0_ADDRESS, OBP1_ADDRESS, SCX_ADDRESS,
+    SCY_ADDRESS, STAT_ADDRESS, VRAM_BEGIN, VRAM_SIZE, WX_ADDRESS, WY_ADDRESS,
+};
+
+pub const INTERRUPT_ENABLE_ADDRESS: u16 = 0xFFFF;
+pub const INTERRUPT_FLAG_ADDRESS: u16 = 0xFF0F;
+
+pub const VBLANK_INTERRUPT_BIT: u8 = 0;
+pub const LCD_STAT_INTERRUPT_BIT: u8 = 1;
+pub const TIMER_INTERRUPT_BIT: u8 = 2;
+pub const SERIAL_INTERRUPT_BIT: u8 = 3;
+pub const JOYPAD_INTERRUPT_BIT: u8 = 4;
+
+const WRAM_BEGIN: usize = 0xC000;
+const WRAM_END: usize = 0xDFFF;
+const WRAM_SIZE: usize = WRAM_END - WRAM_BEGIN + 1;
+
+const ECHO_BEGIN: usize = 0xE000;
+const ECHO_END: usize = 0xFDFF;
+
+const UNUSABLE_BEGIN: usize = 0xFEA0;
+const UNUSABLE_END: usize = 0xFEFF;
+
+const IO_BEGIN: usize = 0xFF00;
+const IO_END: usize = 0xFF7F;
+const IO_SIZE: usize = IO_END - IO_BEGIN + 1;
+
+const HRAM_BEGIN: usize = 0xFF80;
+const HRAM_END: usize = 0xFFFE;
+const HRAM_SIZE: usize = HRAM_END - HRAM_BEGIN + 1;
+
+const OAM_SIZE: usize = OAM_END - OAM_BEGIN + 1;
+const APU_REGISTER_COUNT: usize = (APU_REGISTERS_END - APU_REGISTERS_BEGIN + 1) as usize;
 
 pub fn get_vram_address(address: u16) -> usize {
     address as usize - VRAM_BEGIN
 }
 
+/// The 64 KiB address space, dispatched by region to whichever device owns
+/// it: cartridge ROM/RAM, the PPU's VRAM/OAM, the APU's registers, work RAM,
+/// high RAM, and a catch-all I/O block for registers no other device claims.
 pub struct MemoryBus {
-    pub memory: [u8; 0xFFFF],
+    wram: [u8; WRAM_SIZE],
+    hram: [u8; HRAM_SIZE],
+    io: [u8; IO_SIZE],
     pub gpu: GPU,
+    pub apu: APU,
+    pub interrupt_enable: u8,
+    pub interrupt_flag: u8,
+    pub cartridge: Option<Cartridge>,
 }
 
 impl MemoryBus {
+    /// Builds a bus with zeroed work/high RAM around an optional loaded
+    /// cartridge. With no cartridge, reads from ROM/external-RAM space
+    /// return 0xFF and writes there are ignored, matching an open GB
+    /// cartridge slot.
+    pub fn new(cartridge: Option<Cartridge>) -> MemoryBus {
+        MemoryBus {
+            wram: [0; WRAM_SIZE],
+            hram: [0; HRAM_SIZE],
+            io: [0; IO_SIZE],
+            gpu: GPU::default(),
+            apu: APU::default(),
+            interrupt_enable: 0,
+            interrupt_flag: 0,
+            cartridge,
+        }
+    }
+
     pub fn read_byte(&self, address: u16) -> u8 {
-        match address as usize {
-            VRAM_BEGIN..VRAM_END => self.gpu.read_memory(get_vram_address(address)),
-            _ => self.memory[address as usize],
+        match address {
+            INTERRUPT_ENABLE_ADDRESS => self.interrupt_enable,
+            INTERRUPT_FLAG_ADDRESS => self.interrupt_flag,
+            APU_REGISTERS_BEGIN..=APU_REGISTERS_END => self.apu.read_byte(address),
+            LCDC_ADDRESS => self.gpu.lcdc,
+            STAT_ADDRESS => self.gpu.stat,
+            SCY_ADDRESS => self.gpu.scy,
+            SCX_ADDRESS => self.gpu.scx,
+            LY_ADDRESS => self.gpu.ly,
+            LYC_ADDRESS => self.gpu.lyc,
+            BGP_ADDRESS => self.gpu.bgp,
+            OBP0_ADDRESS => self.gpu.obp0,
+            OBP1_ADDRESS => self.gpu.obp1,
+            WY_ADDRESS => self.gpu.wy,
+            WX_ADDRESS => self.gpu.wx,
+            _ if (OAM_BEGIN..=OAM_END).contains(&(address as usize)) => {
+                self.gpu.read_oam(address as usize - OAM_BEGIN)
+            }
+            0x0000..=0x7FFF => match &self.cartridge {
+                Some(cartridge) => cartridge.read_rom(address),
+                None => 0xFF,
+            },
+            0x8000..=0x9FFF => self.gpu.read_memory(get_vram_address(address)),
+            0xA000..=0xBFFF => match &self.cartridge {
+                Some(cartridge) => cartridge.read_ram(address),
+                None => 0xFF,
+            },
+            _ if (WRAM_BEGIN..=WRAM_END).contains(&(address as usize)) => {
+                self.wram[address as usize - WRAM_BEGIN]
+            }
+            _ if (ECHO_BEGIN..=ECHO_END).contains(&(address as usize)) => {
+                self.wram[address as usize - ECHO_BEGIN]
+            }
+            _ if (UNUSABLE_BEGIN..=UNUSABLE_END).contains(&(address as usize)) => 0xFF,
+            _ if (HRAM_BEGIN..=HRAM_END).contains(&(address as usize)) => {
+                self.hram[address as usize - HRAM_BEGIN]
+            }
+            _ => self.io[address as usize - IO_BEGIN],
         }
     }
 
     pub fn write_byte(&mut self, address: u16, value: u8) {
-        match address as usize {
-            VRAM_BEGIN..VRAM_END => self.gpu.write_memory(get_vram_address(address), value),
-            _ => self.memory[address as usize] = value,
+        match address {
+            INTERRUPT_ENABLE_ADDRESS => self.interrupt_enable = value,
+            INTERRUPT_FLAG_ADDRESS => self.interrupt_flag = value,
+            APU_REGISTERS_BEGIN..=APU_REGISTERS_END => self.apu.write_byte(address, value),
+            LCDC_ADDRESS => self.gpu.lcdc = value,
+            STAT_ADDRESS => self.gpu.stat = (self.gpu.stat & 0x07) | (value & !0x07),
+            SCY_ADDRESS => self.gpu.scy = value,
+            SCX_ADDRESS => self.gpu.scx = value,
+            LY_ADDRESS => {}
+            LYC_ADDRESS => self.gpu.lyc = value,
+            BGP_ADDRESS => self.gpu.bgp = value,
+            OBP0_ADDRESS => self.gpu.obp0 = value,
+            OBP1_ADDRESS => self.gpu.obp1 = value,
+            WY_ADDRESS => self.gpu.wy = value,
+            WX_ADDRESS => self.gpu.wx = value,
+            _ if (OAM_BEGIN..=OAM_END).contains(&(address as usize)) => {
+                self.gpu.write_oam(address as usize - OAM_BEGIN, value)
+            }
+            0x0000..=0x7FFF => {
+                if let Some(cartridge) = &mut self.cartridge {
+                    cartridge.write_rom(address, value);
+                }
+            }
+            0x8000..=0x9FFF => self.gpu.write_memory(get_vram_address(address), value),
+            0xA000..=0xBFFF => {
+                if let Some(cartridge) = &mut self.cartridge {
+                    cartridge.write_ram(address, value);
+                }
+            }
+            _ if (WRAM_BEGIN..=WRAM_END).contains(&(address as usize)) => {
+                self.wram[address as usize - WRAM_BEGIN] = value
+            }
+            _ if (ECHO_BEGIN..=ECHO_END).contains(&(address as usize)) => {
+                self.wram[address as usize - ECHO_BEGIN] = value
+            }
+            _ if (UNUSABLE_BEGIN..=UNUSABLE_END).contains(&(address as usize)) => {}
+            _ if (HRAM_BEGIN..=HRAM_END).contains(&(address as usize)) => {
+                self.hram[address as usize - HRAM_BEGIN] = value
+            }
+            _ => self.io[address as usize - IO_BEGIN] = value,
+        }
+    }
+
+    /// Advances peripherals clocked off the CPU's cycle counter (the APU and
+    /// the PPU) by the M-cycles an instruction just consumed, raising
+    /// VBlank/LCD-STAT interrupts as the PPU's mode state machine crosses
+    /// line boundaries.
+    pub fn tick(&mut self, m_cycles: u8) {
+        self.apu.step(m_cycles);
+
+        let interrupts = self.gpu.step(m_cycles as u32 * 4);
+        if interrupts.vblank {
+            self.request_interrupt(VBLANK_INTERRUPT_BIT);
+        }
+        if interrupts.lcd_stat {
+            self.request_interrupt(LCD_STAT_INTERRUPT_BIT);
+        }
+    }
+
+    /// Sets the corresponding `IF` bit, requesting an interrupt of the given kind.
+    pub fn request_interrupt(&mut self, bit: u8) {
+        self.interrupt_flag |= 1 << bit;
+    }
+
+    /// Serializes work RAM, high RAM, the I/O block, the interrupt
+    /// registers, VRAM, OAM, the GPU's registers, the APU's registers, and
+    /// any loaded cartridge's bank state for a whole-machine save state.
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut buf =
+            Vec::with_capacity(WRAM_SIZE + HRAM_SIZE + IO_SIZE + VRAM_SIZE + OAM_SIZE + 3);
+
+        buf.extend_from_slice(&self.wram);
+        buf.extend_from_slice(&self.hram);
+        buf.extend_from_slice(&self.io);
+        buf.push(self.interrupt_enable);
+        buf.push(self.interrupt_flag);
+        buf.extend_from_slice(&self.gpu.vram_snapshot());
+        buf.extend_from_slice(&self.gpu.oam_snapshot());
+        buf.extend_from_slice(&self.gpu.registers_snapshot());
+        buf.extend_from_slice(&self.apu.registers_snapshot());
+
+        match &self.cartridge {
+            Some(cartridge) => {
+                buf.push(1);
+                buf.extend_from_slice(&cartridge.snapshot());
+            }
+            None => buf.push(0),
+        }
+
+        buf
+    }
+
+    /// Restores state previously produced by [`MemoryBus::snapshot`]. Errors
+    /// if the snapshot's cartridge-presence byte doesn't match whether a
+    /// cartridge is currently loaded, rather than silently dropping or
+    /// leaving stale cartridge RAM in place — a save state only makes sense
+    /// restored into the same kind of machine it was taken from.
+    pub fn restore(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        let fixed_size = WRAM_SIZE
+            + HRAM_SIZE
+            + IO_SIZE
+            + 2
+            + VRAM_SIZE
+            + OAM_SIZE
+            + 11
+            + APU_REGISTER_COUNT
+            + 1;
+
+        if bytes.len() < fixed_size {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "save state is truncated",
+            ));
+        }
+
+        let mut cursor = 0;
+
+        self.wram.copy_from_slice(&bytes[cursor..cursor + WRAM_SIZE]);
+        cursor += WRAM_SIZE;
+
+        self.hram.copy_from_slice(&bytes[cursor..cursor + HRAM_SIZE]);
+        cursor += HRAM_SIZE;
+
+        self.io.copy_from_slice(&bytes[cursor..cursor + IO_SIZE]);
+        cursor += IO_SIZE;
+
+        self.interrupt_enable = bytes[cursor];
+        self.interrupt_flag = bytes[cursor + 1];
+        cursor += 2;
+
+        self.gpu.restore_vram(&bytes[cursor..cursor + VRAM_SIZE]);
+        cursor += VRAM_SIZE;
+
+        self.gpu.restore_oam(&bytes[cursor..cursor + OAM_SIZE]);
+        cursor += OAM_SIZE;
+
+        let gpu_registers: [u8; 11] = bytes[cursor..cursor + 11].try_into().unwrap();
+        self.gpu.restore_registers(&gpu_registers);
+        cursor += 11;
+
+        self.apu
+            .restore_registers(&bytes[cursor..cursor + APU_REGISTER_COUNT]);
+        cursor += APU_REGISTER_COUNT;
+
+        let snapshot_has_cartridge = bytes[cursor] == 1;
+        cursor += 1;
+
+        match (&mut self.cartridge, snapshot_has_cartridge) {
+            (Some(cartridge), true) => cartridge.restore(&bytes[cursor..])?,
+            (None, false) => {}
+            (Some(_), false) | (None, true) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "save state's cartridge presence does not match the currently loaded cartridge",
+                ));
+            }
         }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rom_with_header() -> Vec<u8> {
+        let mut rom = vec![0u8; 0x8000];
+        rom[0x147] = 0x00; // cartridge type: ROM only
+        rom[0x149] = 0x00; // RAM size: none
+        rom
+    }
+
+    #[test]
+    fn snapshot_round_trips_without_a_cartridge() {
+        let mut bus = MemoryBus::new(None);
+        bus.wram[0] = 0x42;
+        bus.interrupt_enable = 0x1F;
+
+        let snapshot = bus.snapshot();
+
+        let mut restored = MemoryBus::new(None);
+        restored.restore(&snapshot).unwrap();
+
+        assert_eq!(restored.wram[0], 0x42);
+        assert_eq!(restored.interrupt_enable, 0x1F);
+    }
+
+    #[test]
+    fn snapshot_round_trips_with_a_cartridge() {
+        let mut bus = MemoryBus::new(Some(Cartridge::from_bytes(rom_with_header(), None)));
+        bus.wram[0] = 0x11;
+
+        let snapshot = bus.snapshot();
+
+        let mut restored = MemoryBus::new(Some(Cartridge::from_bytes(rom_with_header(), None)));
+        restored.restore(&snapshot).unwrap();
+
+        assert_eq!(restored.wram[0], 0x11);
+    }
+
+    #[test]
+    fn restore_rejects_a_cartridge_presence_mismatch() {
+        let bus_without_cartridge = MemoryBus::new(None);
+        let snapshot = bus_without_cartridge.snapshot();
+
+        let mut bus_with_cartridge =
+            MemoryBus::new(Some(Cartridge::from_bytes(rom_with_header(), None)));
+
+        assert!(bus_with_cartridge.restore(&snapshot).is_err());
+    }
+
+    #[test]
+    fn restore_rejects_the_inverse_cartridge_presence_mismatch() {
+        let bus_with_cartridge = MemoryBus::new(Some(Cartridge::from_bytes(rom_with_header(), None)));
+        let snapshot = bus_with_cartridge.snapshot();
+
+        let mut bus_without_cartridge = MemoryBus::new(None);
+
+        assert!(bus_without_cartridge.restore(&snapshot).is_err());
+    }
+
+    #[test]
+    fn snapshot_round_trips_gpu_and_apu_state() {
+        let mut bus = MemoryBus::new(None);
+        bus.write_byte(LCDC_ADDRESS, 0x91);
+        bus.write_byte(SCY_ADDRESS, 0x07);
+        bus.write_byte(BGP_ADDRESS, 0xE4);
+        bus.write_byte(OAM_BEGIN as u16, 0x5A);
+        bus.write_byte(0xFF11, 0x80);
+
+        let snapshot = bus.snapshot();
+
+        let mut restored = MemoryBus::new(None);
+        restored.restore(&snapshot).unwrap();
+
+        assert_eq!(restored.read_byte(LCDC_ADDRESS), 0x91);
+        assert_eq!(restored.read_byte(SCY_ADDRESS), 0x07);
+        assert_eq!(restored.read_byte(BGP_ADDRESS), 0xE4);
+        assert_eq!(restored.read_byte(OAM_BEGIN as u16), 0x5A);
+        assert_eq!(restored.read_byte(0xFF11), 0x80);
+    }
+
+    #[test]
+    fn restore_rejects_a_truncated_save_state_instead_of_panicking() {
+        let bus = MemoryBus::new(None);
+        let mut snapshot = bus.snapshot();
+        snapshot.truncate(snapshot.len() - 1);
+
+        let mut restored = MemoryBus::new(None);
+
+        assert!(restored.restore(&snapshot).is_err());
     }
 }